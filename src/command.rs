@@ -0,0 +1,227 @@
+//! The commands this crate runs, recorded so errors can show what was executed
+
+use crate::{Error, Result};
+use std::cell::Cell;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::Read;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Shown in place of passwords and passphrases
+pub(crate) const REDACTED: &str = "<redacted>";
+
+/// How often a command with a timeout is checked for completion
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+static TIMEOUT: Mutex<Option<Duration>> = Mutex::new(None);
+
+thread_local! {
+    static SCOPED_TIMEOUT: Cell<Option<Option<Duration>>> = const { Cell::new(None) };
+}
+
+/// Set the time every command may take before it is killed, `None` (the default) waits
+/// forever. Applies to all threads unless overridden by `with_timeout`
+pub fn set_timeout(timeout: Option<Duration>) {
+    *TIMEOUT.lock().unwrap_or_else(|err| err.into_inner()) = timeout;
+}
+
+/// Run `f` with a different timeout for the commands it runs on this thread
+pub fn with_timeout<T>(timeout: Option<Duration>, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<Option<Duration>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED_TIMEOUT.set(self.0);
+        }
+    }
+    let _restore = Restore(SCOPED_TIMEOUT.replace(Some(timeout)));
+    f()
+}
+
+#[cfg(test)]
+type Reply = Box<dyn FnMut(&CommandLine) -> Output>;
+
+#[cfg(test)]
+thread_local! {
+    static FAKE: std::cell::RefCell<Option<Reply>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Answer the commands `f` runs on this thread with `reply` instead of spawning them
+#[cfg(test)]
+pub(crate) fn fake<T>(
+    reply: impl FnMut(&CommandLine) -> Output + 'static,
+    f: impl FnOnce() -> T,
+) -> T {
+    struct Restore(Option<Reply>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            FAKE.set(self.0.take());
+        }
+    }
+    let _restore = Restore(FAKE.replace(Some(Box::new(reply))));
+    f()
+}
+
+fn timeout() -> Option<Duration> {
+    SCOPED_TIMEOUT
+        .get()
+        .unwrap_or_else(|| *TIMEOUT.lock().unwrap_or_else(|err| err.into_inner()))
+}
+
+/// Program and arguments of an executed command, with secrets redacted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandLine {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl fmt::Display for CommandLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.program)?;
+        for arg in &self.args {
+            // Flags, numbers and addresses are left bare, names are quoted
+            let bare = arg == REDACTED
+                || arg.starts_with('-')
+                || !arg.is_empty() && arg.chars().all(|c| c.is_ascii_digit() || ".:/".contains(c));
+            if bare {
+                write!(f, " {arg}")?;
+            } else {
+                write!(f, " {arg:?}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A `Command` that records its arguments as it is built
+pub(crate) struct Cmd {
+    command: Command,
+    line: CommandLine,
+}
+
+impl Cmd {
+    pub(crate) fn new(program: &str) -> Self {
+        Self {
+            command: Command::new(program),
+            line: CommandLine {
+                program: program.to_string(),
+                args: Vec::new(),
+            },
+        }
+    }
+
+    pub(crate) fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        let arg = arg.as_ref();
+        self.line.args.push(arg.to_string_lossy().into_owned());
+        self.command.arg(arg);
+        self
+    }
+
+    pub(crate) fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        for arg in args {
+            self.arg(arg);
+        }
+        self
+    }
+
+    /// Pass a password, it is redacted from the recorded command line
+    pub(crate) fn secret(&mut self, secret: &str) -> &mut Self {
+        self.line.args.push(REDACTED.to_string());
+        self.command.arg(secret);
+        self
+    }
+
+    pub(crate) fn line(&self) -> &CommandLine {
+        &self.line
+    }
+
+    /// Run to completion and capture stdout and stderr, whatever the exit status.
+    /// The command is killed if it outlives the timeout
+    pub(crate) fn output(&mut self) -> Result<Output> {
+        #[cfg(test)]
+        if let Some(output) =
+            FAKE.with_borrow_mut(|fake| fake.as_mut().map(|reply| reply(&self.line)))
+        {
+            return Ok(output);
+        }
+        let spawn = |source| Error::Spawn {
+            command: self.line.clone(),
+            source,
+        };
+        self.command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let Some(timeout) = timeout() else {
+            return self.command.output().map_err(spawn);
+        };
+        let start = Instant::now();
+        let mut child = self.command.spawn().map_err(spawn)?;
+        // Drain the pipes while waiting so a chatty command cannot block on a full pipe
+        let stdout = drain(child.stdout.take());
+        let stderr = drain(child.stderr.take());
+        // Reap the killed child, the readers finish once its pipes close
+        let stop = |child: &mut Child| {
+            let _ = child.kill();
+            let _ = child.wait();
+        };
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) => {}
+                Err(err) => {
+                    stop(&mut child);
+                    return Err(spawn(err));
+                }
+            }
+            let waited = start.elapsed();
+            if waited >= timeout {
+                stop(&mut child);
+                return Err(Error::TimedOut {
+                    command: self.line.clone(),
+                    waited,
+                });
+            }
+            thread::sleep(POLL_INTERVAL.min(timeout - waited));
+        };
+        // A background process the command started can keep the pipes open after it exits
+        let deadline = start + timeout;
+        let collect = |pipe: &Receiver<Vec<u8>>| match pipe
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        {
+            Ok(buf) => Some(buf),
+            Err(RecvTimeoutError::Disconnected) => Some(Vec::new()),
+            Err(RecvTimeoutError::Timeout) => None,
+        };
+        match (collect(&stdout), collect(&stderr)) {
+            (Some(stdout), Some(stderr)) => Ok(Output {
+                status,
+                stdout,
+                stderr,
+            }),
+            _ => Err(Error::TimedOut {
+                command: self.line.clone(),
+                waited: start.elapsed(),
+            }),
+        }
+    }
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> Receiver<Vec<u8>> {
+    let (send, receive) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        let _ = send.send(buf);
+    });
+    receive
+}
@@ -0,0 +1,214 @@
+//! The error type returned by every function in this crate
+
+use crate::CommandLine;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::process::{ExitStatus, Output};
+use std::time::Duration;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The command could not be started
+    Spawn {
+        command: CommandLine,
+        source: io::Error,
+    },
+    /// The command exited non-zero or printed an error, with the output it captured
+    Failed {
+        command: CommandLine,
+        status: ExitStatus,
+        stdout: String,
+        stderr: String,
+    },
+    /// The command did not finish within the timeout and was killed
+    TimedOut {
+        command: CommandLine,
+        waited: Duration,
+    },
+    /// The command needs to run as root or with admin rights
+    PermissionDenied {
+        command: CommandLine,
+        message: String,
+    },
+    /// The network service does not exist, `available` lists the ones that do.
+    /// `command` is the invocation that reported it, if one ran
+    UnknownService {
+        command: Option<CommandLine>,
+        requested: String,
+        available: Vec<String>,
+    },
+    /// The output of `command` could not be parsed, `None` for a string parsed with
+    /// `FromStr`
+    Parse {
+        command: Option<CommandLine>,
+        text: String,
+    },
+    /// The subcommand is not available on this version of macOS
+    Unsupported { command: String },
+    /// FTP proxies were removed from networksetup in macOS 13 (Ventura)
+    FtpProxyUnsupported,
+    /// A file could not be accessed
+    Io(io::Error),
+    /// Rejected input or an unmet precondition, usually holding one of this crate's
+    /// error types (see `downcast_ref`)
+    Other {
+        kind: ErrorKind,
+        source: Box<dyn StdError + Send + Sync>,
+    },
+}
+
+impl Error {
+    pub(crate) fn new<E>(kind: ErrorKind, source: E) -> Self
+    where
+        E: Into<Box<dyn StdError + Send + Sync>>,
+    {
+        Error::Other {
+            kind,
+            source: source.into(),
+        }
+    }
+
+    pub(crate) fn other<E>(source: E) -> Self
+    where
+        E: Into<Box<dyn StdError + Send + Sync>>,
+    {
+        Self::new(ErrorKind::Other, source)
+    }
+
+    pub(crate) fn failed(command: &CommandLine, output: Output) -> Self {
+        Error::Failed {
+            command: command.clone(),
+            status: output.status,
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+    }
+
+    /// What the command printed about the failure, or the whole message otherwise
+    pub(crate) fn message(&self) -> String {
+        match self {
+            Error::Failed { stdout, stderr, .. } => [stderr.trim(), stdout.trim()]
+                .into_iter()
+                .find(|s| !s.is_empty())
+                .unwrap_or_default()
+                .to_string(),
+            Error::PermissionDenied { message, .. } => message.clone(),
+            err => err.to_string(),
+        }
+    }
+
+    /// Broad category of the error, matching the `io::ErrorKind` returned before
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Spawn { source, .. } => source.kind(),
+            Error::Io(err) => err.kind(),
+            Error::Failed { .. } => ErrorKind::Other,
+            Error::TimedOut { .. } => ErrorKind::TimedOut,
+            Error::PermissionDenied { .. } => ErrorKind::PermissionDenied,
+            Error::UnknownService { .. } => ErrorKind::NotFound,
+            Error::Parse { .. } => ErrorKind::InvalidData,
+            Error::Unsupported { .. } | Error::FtpProxyUnsupported => ErrorKind::Unsupported,
+            Error::Other { kind, .. } => *kind,
+        }
+    }
+
+    /// The crate's typed error this wraps, e.g. `MissingServices` or `NotApplied`
+    pub fn downcast_ref<T: StdError + 'static>(&self) -> Option<&T> {
+        match self {
+            Error::Io(err) => err.get_ref()?.downcast_ref(),
+            Error::Other { source, .. } => source.downcast_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Spawn { command, source } => {
+                write!(f, "{command} could not be started: {source}")
+            }
+            Error::Io(err) => err.fmt(f),
+            Error::Failed {
+                command,
+                status,
+                stdout,
+                stderr,
+            } => {
+                // Some errors (e.g. an unrecognized service) are printed to stdout
+                match [stderr.trim(), stdout.trim()]
+                    .into_iter()
+                    .find(|s| !s.is_empty())
+                {
+                    Some(msg) => write!(f, "{command} failed: {msg}"),
+                    None => write!(f, "{command} failed with {status}"),
+                }
+            }
+            Error::TimedOut { command, waited } => {
+                write!(f, "{command} timed out after {waited:?}")
+            }
+            Error::PermissionDenied { command, message } => {
+                write!(f, "{command} requires admin rights: {message}")
+            }
+            Error::UnknownService {
+                command,
+                requested,
+                available,
+            } => {
+                if let Some(command) = command {
+                    write!(f, "{command} failed: ")?;
+                }
+                write!(f, "{requested} is not a recognized network service")?;
+                if !available.is_empty() {
+                    write!(f, ", available: {}", available.join(", "))?;
+                }
+                Ok(())
+            }
+            Error::Parse {
+                command: Some(command),
+                text,
+            } => write!(f, "{command} printed unexpected output: {text}"),
+            Error::Parse {
+                command: None,
+                text,
+            } => write!(f, "unexpected output: {text}"),
+            Error::Unsupported { command } => {
+                write!(f, "networksetup {command} is not supported on this system")
+            }
+            Error::FtpProxyUnsupported => {
+                write!(f, "FTP proxies are not supported on this version of macOS")
+            }
+            Error::Other { source, .. } => source.fmt(f),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::Spawn { source, .. } => Some(source),
+            Error::Io(err) => err.source(),
+            Error::Other { source, .. } => source.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// Lets callers that still return `io::Result` keep using `?`
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            err => io::Error::new(err.kind(), err),
+        }
+    }
+}
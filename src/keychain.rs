@@ -0,0 +1,32 @@
+//! Saved Wi-Fi passwords from the keychain, using the `security` command
+
+use crate::{Cmd, Error, Result};
+
+/// Exit code of `security` when the item does not exist
+const ITEM_NOT_FOUND: i32 = 44;
+
+/// Password of a preferred wireless network, `None` if it is not in the keychain.
+/// Fails with `PermissionDenied` when access to the item is denied
+pub fn wifi_password(ssid: &str) -> Result<Option<String>> {
+    let mut cmd = Cmd::new("security");
+    cmd.args(["find-generic-password", "-D", "AirPort network password"])
+        .args(["-a", ssid, "-w"]);
+    let output = cmd.output()?;
+    if output.status.success() {
+        let password = String::from_utf8_lossy(&output.stdout);
+        return Ok(Some(password.trim_end_matches('\n').to_string()));
+    }
+    if output.status.code() == Some(ITEM_NOT_FOUND) {
+        return Ok(None);
+    }
+    let msg = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    let lower = msg.to_ascii_lowercase();
+    if lower.contains("not allowed") || lower.contains("denied") || lower.contains("canceled") {
+        Err(Error::PermissionDenied {
+            command: cmd.line().clone(),
+            message: msg,
+        })
+    } else {
+        Err(Error::failed(cmd.line(), output))
+    }
+}
@@ -1,15 +1,61 @@
-use std::io::Result;
-use std::process::{Command, ExitStatus, Stdio};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod command;
+mod error;
+#[cfg(feature = "keychain")]
+pub mod keychain;
+pub mod resolver;
+
+use command::Cmd;
+pub use command::{set_timeout, with_timeout, CommandLine};
+pub use error::{Error, Result};
 
 const ON: &str = "on";
 const OFF: &str = "off";
 
-/// Off / On / Set new value
+/// Off / On / Set new value / Clear the stored value and turn off
 #[derive(Debug, Clone)]
 pub enum Config<T> {
     Off,
     On,
     Value(T),
+    Clear,
+}
+
+/// Proxy authentication, `Debug` hides the password
+#[derive(Clone)]
+pub enum Auth<'a> {
+    /// Leave the authentication setting unchanged
+    None,
+    /// Turn authentication off
+    Disabled,
+    /// Turn authentication on with username and password
+    Credentials(&'a str, &'a str),
+}
+
+impl fmt::Debug for Auth<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Auth::None => write!(f, "None"),
+            Auth::Disabled => write!(f, "Disabled"),
+            Auth::Credentials(username, _) => f
+                .debug_tuple("Credentials")
+                .field(username)
+                .field(&format_args!("{}", command::REDACTED))
+                .finish(),
+        }
+    }
 }
 
 /// Proxy address configuration
@@ -17,7 +63,7 @@ pub enum Config<T> {
 pub struct Address<'a> {
     host: &'a str,
     port: &'a str,
-    auth: Option<(&'a str, &'a str)>,
+    auth: Auth<'a>,
 }
 
 impl<'a> Address<'a> {
@@ -25,14 +71,85 @@ impl<'a> Address<'a> {
         Self {
             host,
             port,
-            auth: None,
+            auth: Auth::None,
         }
     }
 
     pub fn auth(&mut self, username: &'a str, password: &'a str) -> &mut Self {
-        self.auth = Some((username, password));
+        self.auth = Auth::Credentials(username, password);
+        self
+    }
+
+    pub fn disable_auth(&mut self) -> &mut Self {
+        self.auth = Auth::Disabled;
         self
     }
+
+    fn apply(&self, cmd: &mut Cmd) {
+        cmd.args([self.host, self.port]);
+        match self.auth {
+            Auth::None => {}
+            Auth::Disabled => {
+                cmd.arg(OFF);
+            }
+            Auth::Credentials(username, password) => {
+                cmd.args([ON, username]).secret(password);
+            }
+        }
+    }
+}
+
+/// A password that is hidden from `Debug`.
+///
+/// A plain string is not wiped when dropped. With the `secrecy` feature a
+/// `secrecy::SecretString` can be passed instead, it is kept as is and zeroized on drop.
+/// Either way networksetup receives the password as an argument, and that copy is not
+/// wiped
+pub struct Secret(Inner);
+
+enum Inner {
+    Plain(String),
+    #[cfg(feature = "secrecy")]
+    Secrecy(secrecy::SecretString),
+}
+
+impl Secret {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(Inner::Plain(secret.into()))
+    }
+
+    pub fn expose(&self) -> &str {
+        match &self.0 {
+            Inner::Plain(secret) => secret,
+            #[cfg(feature = "secrecy")]
+            Inner::Secrecy(secret) => secrecy::ExposeSecret::expose_secret(secret),
+        }
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(secret: &str) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl From<String> for Secret {
+    fn from(secret: String) -> Self {
+        Self::new(secret)
+    }
+}
+
+#[cfg(feature = "secrecy")]
+impl From<secrecy::SecretString> for Secret {
+    fn from(secret: secrecy::SecretString) -> Self {
+        Self(Inner::Secrecy(secret))
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret(..)")
+    }
 }
 
 /// Network service
@@ -45,6 +162,383 @@ pub enum Network<'a> {
     Name(&'a str),
 }
 
+/// Current proxy configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxySettings {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub authenticated: bool,
+}
+
+impl ProxySettings {
+    /// Whether a server has ever been set, even if the proxy is currently disabled
+    pub fn is_configured(&self) -> bool {
+        !self.host.is_empty()
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        let mut enabled = None;
+        let mut host = String::new();
+        let mut port = 0;
+        let mut authenticated = false;
+        for (key, value) in fields(text) {
+            match key {
+                "Enabled" => enabled = Some(value == "Yes"),
+                "Server" => host = value.to_string(),
+                "Port" if !value.is_empty() => {
+                    port = value.parse().map_err(|_| invalid_data(text))?;
+                }
+                "Authenticated Proxy Enabled" => authenticated = value == "1",
+                _ => {}
+            }
+        }
+        match enabled {
+            Some(enabled) => Ok(Self {
+                enabled,
+                host,
+                port,
+                authenticated,
+            }),
+            None => Err(invalid_data(text)),
+        }
+    }
+}
+
+/// Current automatic proxy configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoProxySettings {
+    pub url: Option<String>,
+    pub enabled: bool,
+}
+
+impl AutoProxySettings {
+    fn parse(text: &str) -> Result<Self> {
+        let mut url = None;
+        let mut enabled = None;
+        for (key, value) in fields(text) {
+            match key {
+                "URL" if value != "(null)" && !value.is_empty() => url = Some(value.to_string()),
+                "Enabled" => enabled = Some(value == "Yes"),
+                _ => {}
+            }
+        }
+        match enabled {
+            Some(enabled) => Ok(Self { url, enabled }),
+            None => Err(invalid_data(text)),
+        }
+    }
+}
+
+/// An entry in networksetup output that is not a valid IP address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidAddress(pub String);
+
+impl fmt::Display for InvalidAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid IP address: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidAddress {}
+
+/// Writing a modified list failed after the current list was read
+#[derive(Debug)]
+pub struct UpdateFailed<T> {
+    pub previous: Vec<T>,
+    pub intended: Vec<T>,
+    pub error: Error,
+}
+
+impl<T: fmt::Debug> fmt::Display for UpdateFailed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to change {:?} to {:?}: {}",
+            self.previous, self.intended, self.error
+        )
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for UpdateFailed<T> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// A bypass entry rejected by `checked_proxy_by_pass_domain`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidBypassDomain {
+    pub entry: String,
+    pub reason: &'static str,
+}
+
+/// Every entry rejected by `checked_proxy_by_pass_domain`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidBypassDomains(pub Vec<InvalidBypassDomain>);
+
+impl fmt::Display for InvalidBypassDomains {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid bypass domains:")?;
+        for invalid in &self.0 {
+            write!(f, " {:?} ({})", invalid.entry, invalid.reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidBypassDomains {}
+
+/// Refused to remove the primary network service without `force`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrimaryService(pub String);
+
+impl fmt::Display for PrimaryService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is the primary network service", self.0)
+    }
+}
+
+impl std::error::Error for PrimaryService {}
+
+/// Enabled network services left out of `order_network_services`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingServices(pub Vec<String>);
+
+impl fmt::Display for MissingServices {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "missing network services: {}", self.0.join(", "))
+    }
+}
+
+impl std::error::Error for MissingServices {}
+
+/// networksetup succeeded, but reading the setting back shows a different value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotApplied {
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for NotApplied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "setting was not applied: expected {}, found {}",
+            self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for NotApplied {}
+
+/// Requested MTU is outside of the range supported by the hardware port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MtuOutOfRange {
+    pub requested: u32,
+    pub min: u32,
+    pub max: u32,
+}
+
+impl fmt::Display for MtuOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "MTU {} is outside of the valid range {}-{}",
+            self.requested, self.min, self.max
+        )
+    }
+}
+
+impl std::error::Error for MtuOutOfRange {}
+
+/// The Wi-Fi radio did not change, e.g. because a profile blocks it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerChangeRejected {
+    pub requested: bool,
+}
+
+impl fmt::Display for PowerChangeRejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.requested { ON } else { OFF };
+        write!(f, "Wi-Fi power could not be turned {state}")
+    }
+}
+
+impl std::error::Error for PowerChangeRejected {}
+
+/// Joining a Wi-Fi network failed, networksetup reports this on stdout with exit code 0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WifiJoinError {
+    /// "Could not find network"
+    NetworkNotFound(String),
+    /// Wrong password or rejected credentials
+    AuthenticationFailed(String),
+    /// "Failed to join network" and anything else networksetup prints
+    Failed(String),
+}
+
+impl WifiJoinError {
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let lower = text.to_ascii_lowercase();
+        if text.is_empty() {
+            None
+        } else if text.contains("Could not find network") {
+            Some(WifiJoinError::NetworkNotFound(text.to_string()))
+        } else if lower.contains("password") || lower.contains("authentication") {
+            Some(WifiJoinError::AuthenticationFailed(text.to_string()))
+        } else if text.contains("Failed to join") || lower.contains("error") {
+            Some(WifiJoinError::Failed(text.to_string()))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for WifiJoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WifiJoinError::NetworkNotFound(msg)
+            | WifiJoinError::AuthenticationFailed(msg)
+            | WifiJoinError::Failed(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for WifiJoinError {}
+
+/// `join_wifi_and_wait` timed out, with the last observed state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinTimedOut {
+    /// SSID the device was associated with
+    pub ssid: Option<String>,
+    /// IPv4 address of the device
+    pub address: Option<Ipv4Addr>,
+}
+
+impl fmt::Display for JoinTimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.ssid, self.address) {
+            (None, _) => write!(f, "timed out before associating with a Wi-Fi network"),
+            (Some(ssid), None) => write!(f, "associated with {ssid} but no address was assigned"),
+            (Some(ssid), Some(addr)) => write!(f, "timed out on {ssid} with address {addr}"),
+        }
+    }
+}
+
+impl std::error::Error for JoinTimedOut {}
+
+/// The SSID is not in the preferred wireless networks list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotInList(pub String);
+
+impl fmt::Display for NotInList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is not in the preferred networks list", self.0)
+    }
+}
+
+impl std::error::Error for NotInList {}
+
+/// The parent device of a VLAN does not support VLANs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedVlanDevice {
+    pub device: String,
+    pub valid: Vec<String>,
+}
+
+impl fmt::Display for UnsupportedVlanDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} does not support VLANs, valid devices: {}",
+            self.device,
+            self.valid.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedVlanDevice {}
+
+/// A device is already a member of another bond
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BondMemberInUse {
+    pub device: String,
+    pub bond: String,
+}
+
+impl fmt::Display for BondMemberInUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is already a member of {}", self.device, self.bond)
+    }
+}
+
+impl std::error::Error for BondMemberInUse {}
+
+/// `connect_pppoe_and_wait` did not reach the connected state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PppoeConnectError {
+    /// Timed out, with the last observed status
+    TimedOut(PppoeStatus),
+    /// The session went back to disconnected while connecting
+    AuthenticationLikelyFailed,
+}
+
+impl fmt::Display for PppoeConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PppoeConnectError::TimedOut(status) => {
+                write!(f, "PPPoE connection timed out, last status: {status:?}")
+            }
+            PppoeConnectError::AuthenticationLikelyFailed => {
+                write!(
+                    f,
+                    "PPPoE disconnected while connecting, authentication likely failed"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PppoeConnectError {}
+
+/// Refused to delete the active location without `force`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveLocation(pub String);
+
+impl fmt::Display for ActiveLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is the current location", self.0)
+    }
+}
+
+impl std::error::Error for ActiveLocation {}
+
+/// Refused to delete the current location because no other location exists to switch to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnlyLocation(pub String);
+
+impl fmt::Display for OnlyLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is the only location", self.0)
+    }
+}
+
+impl std::error::Error for OnlyLocation {}
+
+/// `export_8021x_profiles` finished without writing any profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoProfilesToExport(pub String);
+
+impl fmt::Display for NoProfilesToExport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} has no 802.1X profiles to export", self.0)
+    }
+}
+
+impl std::error::Error for NoProfilesToExport {}
+
 impl<'a> Network<'a> {
     fn as_str(&self) -> &'a str {
         match self {
@@ -57,152 +551,4021 @@ impl<'a> Network<'a> {
     }
 }
 
-fn cmd() -> Command {
-    let mut cmd = Command::new("networksetup");
-    cmd.stdout(Stdio::null());
-    cmd.stderr(Stdio::null());
+fn cmd() -> Cmd {
+    Cmd::new("networksetup")
+}
+
+/// Run the command, networksetup's message becomes the error
+fn execute(cmd: &mut Cmd) -> Result<Output> {
+    let output = cmd.output()?;
+    match check(cmd.line(), output) {
+        Err(Error::UnknownService {
+            command,
+            requested,
+            available,
+        }) if available.is_empty() => Err(Error::UnknownService {
+            command,
+            requested,
+            available: service_names().unwrap_or_default(),
+        }),
+        result => result,
+    }
+}
+
+/// Classify the output of a finished command
+fn check(line: &CommandLine, output: Output) -> Result<Output> {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Some errors (e.g. an unrecognized service) are printed to stdout
+    if output.status.success() && !stdout.contains("** Error") {
+        return Ok(output);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if let Some(message) = [stderr.trim(), stdout.trim()]
+        .into_iter()
+        .find(|msg| denied(msg))
+    {
+        return Err(Error::PermissionDenied {
+            command: line.clone(),
+            message: message.to_string(),
+        });
+    }
+    if let Some(err) = unknown_service(line, &stdout).or_else(|| unknown_service(line, &stderr)) {
+        return Err(err);
+    }
+    Err(Error::failed(line, output))
+}
+
+/// Parse "<name> is not a recognized network service", networksetup may list the valid
+/// services after it
+fn unknown_service(command: &CommandLine, text: &str) -> Option<Error> {
+    const MESSAGE: &str = " is not a recognized network service";
+    let mut lines = text.lines().skip_while(|line| !line.contains(MESSAGE));
+    let line = lines.next()?;
+    let requested = line[..line.find(MESSAGE)?].trim_start_matches("** Error: ");
+    // `** Error: ...` lines are not services, a single `*` marks a disabled one
+    let available = lines
+        .map(str::trim)
+        .filter(|line| !line.starts_with("**"))
+        .map(|line| line.trim_start_matches('*').trim())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect();
+    Some(Error::UnknownService {
+        command: Some(command.clone()),
+        requested: requested.trim().to_string(),
+        available,
+    })
+}
+
+/// How networksetup and the tools run next to it report missing admin rights, e.g.
+/// "** Error: Command requires admin privileges." or airport's "Operation not permitted".
+/// killall only looks at the user's own processes without root, so it cannot find
+/// mDNSResponder
+const DENIED: &[&str] = &[
+    "belonging to you",
+    "requires admin",
+    "must be root",
+    "must run as root",
+    "must be run as root",
+    "operation not permitted",
+];
+
+fn denied(text: &str) -> bool {
+    let text = text.to_ascii_lowercase();
+    DENIED.iter().any(|phrase| text.contains(phrase))
+}
+
+/// Run the command and capture stdout
+fn output(cmd: &mut Cmd) -> Result<String> {
+    let output = execute(cmd)?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Run the command and parse its stdout, a parse error names the command
+fn parsed<T>(cmd: &mut Cmd, parse: impl FnOnce(&str) -> Result<T>) -> Result<T> {
+    let text = output(cmd)?;
+    parse(&text).map_err(|err| match err {
+        Error::Parse {
+            command: None,
+            text,
+        } => Error::Parse {
+            command: Some(cmd.line().clone()),
+            text,
+        },
+        err => err,
+    })
+}
+
+/// Normalize a bypass entry: hostname, `*.wildcard`, IP address or CIDR range
+fn bypass_domain(entry: &str) -> std::result::Result<&str, &'static str> {
+    let entry = match entry.split_once("://") {
+        Some((_, rest)) => rest.split('/').next().unwrap_or(rest),
+        None => entry,
+    };
+    if entry.is_empty() {
+        return Err("empty entry");
+    }
+    if entry.contains(char::is_whitespace) {
+        return Err("contains whitespace");
+    }
+    if entry.parse::<IpAddr>().is_ok() {
+        return Ok(entry);
+    }
+    if let Some((addr, prefix)) = entry.split_once('/') {
+        let max = match addr.parse::<IpAddr>() {
+            Ok(IpAddr::V6(_)) => 128,
+            Ok(IpAddr::V4(_)) => 32,
+            // Short forms such as `169.254/16`
+            Err(_)
+                if addr.split('.').count() <= 4
+                    && addr.split('.').all(|octet| octet.parse::<u8>().is_ok()) =>
+            {
+                32
+            }
+            Err(_) => return Err("invalid network address"),
+        };
+        return match prefix.parse::<u8>() {
+            Ok(len) if len <= max => Ok(entry),
+            _ => Err("invalid prefix length"),
+        };
+    }
+    let host = entry.strip_prefix("*.").unwrap_or(entry);
+    let valid = host.split('.').all(|label| {
+        !label.is_empty()
+            && label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    });
+    if valid {
+        Ok(entry)
+    } else {
+        Err("invalid hostname")
+    }
+}
+
+/// Replace a list setting, networksetup expects `Empty` to clear it
+fn set_list(flag: &str, network: &Network, items: &[&str]) -> Cmd {
+    let mut cmd = cmd();
+    cmd.args([flag, network.as_str()]);
+    if items.is_empty() {
+        cmd.arg("Empty");
+    } else {
+        cmd.args(items);
+    }
     cmd
 }
 
+/// Write back a list that was read from networksetup and then modified
+fn update<T>(flag: &str, network: &Network, previous: Vec<T>, intended: Vec<T>) -> Result<()>
+where
+    T: ToString + fmt::Debug + Send + Sync + 'static,
+{
+    let items = intended.iter().map(T::to_string).collect::<Vec<_>>();
+    let items = items.iter().map(String::as_str).collect::<Vec<_>>();
+    match execute(&mut set_list(flag, network, &items)) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(Error::new(
+            error.kind(),
+            UpdateFailed {
+                previous,
+                intended,
+                error,
+            },
+        )),
+    }
+}
+
+/// Run the command, a non-zero exit becomes the error
+fn run(cmd: &mut Cmd) -> Result<()> {
+    execute(cmd).map(|_| ())
+}
+
+/// Wipe the stored value, then turn the setting off; both steps must succeed
+fn clear(network: &Network, set: &str, values: &[&str], state: &str) -> Result<()> {
+    let mut wipe = cmd();
+    wipe.args([set, network.as_str()]).args(values);
+    run(&mut wipe)?;
+    let mut off = cmd();
+    off.args([state, network.as_str(), OFF]);
+    run(&mut off)
+}
+
+/// Recognize the message of a networksetup without FTP proxy support
+fn ftp_removed(text: &str) -> bool {
+    let text = text.to_lowercase();
+    [
+        "not supported",
+        "no longer supported",
+        "unrecognized command",
+        "invalid command",
+    ]
+    .iter()
+    .any(|message| text.contains(message))
+}
+
+fn ftp_error(err: Error) -> Error {
+    if ftp_removed(&err.message()) {
+        Error::FtpProxyUnsupported
+    } else {
+        err
+    }
+}
+
+fn ftp(cmd: &mut Cmd) -> Result<()> {
+    run(cmd).map_err(ftp_error)
+}
+
+fn invalid_data(text: &str) -> Error {
+    Error::Parse {
+        command: None,
+        text: text.trim().to_string(),
+    }
+}
+
+/// Parse a `Key: On/Off` line
+fn on_off(text: &str, name: &str) -> Result<bool> {
+    match field(text, name) {
+        Some("On") => Ok(true),
+        Some("Off") => Ok(false),
+        _ => Err(invalid_data(text)),
+    }
+}
+
+/// One entry per line, or none for the "There aren't any ... set" / "There are no ..." message
+fn entries(text: &str) -> Vec<String> {
+    let text = text.trim_start();
+    if text.starts_with("There aren't any") || text.starts_with("There are no") {
+        return Vec::new();
+    }
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn ip_addr(entry: &str, kind: ErrorKind) -> Result<IpAddr> {
+    entry
+        .parse()
+        .map_err(|_| Error::new(kind, InvalidAddress(entry.to_string())))
+}
+
+/// A value that networksetup may print as `none`
+fn optional<T: FromStr>(value: &str) -> Option<T> {
+    match value {
+        "" | "none" | "(null)" => None,
+        _ => value.parse().ok(),
+    }
+}
+
+/// Compare a value read back after a change with the expected one
+fn verify<T: PartialEq + fmt::Debug>(expected: T, actual: T) -> Result<()> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(Error::other(NotApplied {
+            expected: format!("{expected:?}"),
+            actual: format!("{actual:?}"),
+        }))
+    }
+}
+
+/// Value of the first `name: value` line
+fn field<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    fields(text)
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// `Key: Value` lines of networksetup output
+fn fields(text: &str) -> impl Iterator<Item = (&str, &str)> {
+    text.lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim(), value.trim()))
+}
+
 /// macOS Proxies: Atuo Proxy Discovery
-pub fn auto_proxy_discovery(network: Network, enable: bool) -> Result<ExitStatus> {
+pub fn auto_proxy_discovery(network: Network, enable: bool) -> Result<()> {
     let mut cmd = cmd();
-    cmd.args(&["-setproxyautodiscovery", network.as_str()]);
+    cmd.args(["-setproxyautodiscovery", network.as_str()]);
     if enable {
         cmd.arg(ON);
     } else {
         cmd.arg(OFF);
     }
-    cmd.status()
+    run(&mut cmd)
+}
+
+/// macOS Proxies: Get Auto Proxy Discovery
+pub fn get_proxy_auto_discovery(network: Network) -> Result<bool> {
+    let mut cmd = cmd();
+    cmd.args(["-getproxyautodiscovery", network.as_str()]);
+    parsed(&mut cmd, |text| on_off(text, "Auto Proxy Discovery"))
 }
 
 /// macOS Proxies: Atuomatic Proxy Configuration
-pub fn auto_proxy(network: Network, url: Config<&str>) -> Result<ExitStatus> {
+pub fn auto_proxy(network: Network, url: Config<&str>) -> Result<()> {
     let mut cmd = cmd();
     match url {
         Config::Off => {
-            cmd.args(&["-setautoproxystate", network.as_str(), OFF]);
+            cmd.args(["-setautoproxystate", network.as_str(), OFF]);
         }
         Config::On => {
-            cmd.args(&["-setautoproxystate", network.as_str(), ON]);
+            cmd.args(["-setautoproxystate", network.as_str(), ON]);
+        }
+        Config::Clear => {
+            return clear(&network, "-setautoproxyurl", &[""], "-setautoproxystate");
         }
         Config::Value(url) => {
-            cmd.args(&["-setautoproxyurl", network.as_str(), url]);
+            cmd.args(["-setautoproxyurl", network.as_str(), url]);
         }
     }
-    cmd.status()
+    run(&mut cmd)
+}
+
+/// macOS Proxies: Get Automatic Proxy Configuration
+pub fn get_auto_proxy(network: Network) -> Result<AutoProxySettings> {
+    let mut cmd = cmd();
+    cmd.args(["-getautoproxyurl", network.as_str()]);
+    parsed(&mut cmd, AutoProxySettings::parse)
 }
 
 /// macOS Proxies: FTP Proxy
-pub fn ftp_proxy(network: Network, setup: Config<&Address>) -> Result<ExitStatus> {
+pub fn ftp_proxy(network: Network, setup: Config<&Address>) -> Result<()> {
+    require(capabilities().ftp_proxy, "-setftpproxy").map_err(|_| Error::FtpProxyUnsupported)?;
     let mut cmd = cmd();
     match setup {
         Config::Off => {
-            cmd.args(&["-setftpproxystate", network.as_str(), OFF]);
+            cmd.args(["-setftpproxystate", network.as_str(), OFF]);
         }
         Config::On => {
-            cmd.args(&["-setftpproxystate", network.as_str(), ON]);
+            cmd.args(["-setftpproxystate", network.as_str(), ON]);
+        }
+        Config::Clear => {
+            return clear(&network, "-setftpproxy", &["", ""], "-setftpproxystate")
+                .map_err(ftp_error);
         }
         Config::Value(addr) => {
-            let mut ops = vec!["-setftpproxy", network.as_str(), addr.host, addr.port];
-            if let Some((username, password)) = addr.auth {
-                ops.extend_from_slice(&[ON, username, password]);
-            }
-            cmd.args(&ops);
+            cmd.args(["-setftpproxy", network.as_str()]);
+            addr.apply(&mut cmd);
         }
     }
-    cmd.status()
+    ftp(&mut cmd)
 }
 
-/// macOS Proxies: Web Proxy (HTTP)
-pub fn web_proxy(network: Network, setup: Config<&Address>) -> Result<ExitStatus> {
+/// macOS Proxies: Get FTP Proxy
+pub fn get_ftp_proxy(network: Network) -> Result<ProxySettings> {
+    require(capabilities().ftp_proxy, "-getftpproxy").map_err(|_| Error::FtpProxyUnsupported)?;
     let mut cmd = cmd();
-    match setup {
-        Config::Off => {
-            cmd.args(&["-setwebproxystate", network.as_str(), OFF]);
-        }
-        Config::On => {
-            cmd.args(&["-setwebproxystate", network.as_str(), ON]);
-        }
-        Config::Value(addr) => {
-            let mut ops = vec!["-setwebproxy", network.as_str(), addr.host, addr.port];
-            if let Some((username, password)) = addr.auth {
-                ops.extend_from_slice(&["on", username, password]);
-            }
-            cmd.args(&ops);
-        }
-    }
-    cmd.status()
+    cmd.args(["-getftpproxy", network.as_str()]);
+    parsed(&mut cmd, ProxySettings::parse).map_err(ftp_error)
 }
 
-/// macOS Proxies: Secure Web Proxy (HTTPS)
-pub fn secure_web_proxy(network: Network, setup: Config<&Address>) -> Result<ExitStatus> {
+/// macOS Proxies: Use Passive FTP Mode (PASV)
+pub fn passive_ftp(network: Network, enable: bool) -> Result<()> {
     let mut cmd = cmd();
-    match setup {
+    cmd.args(["-setpassiveftp", network.as_str()]);
+    if enable {
+        cmd.arg(ON);
+    } else {
+        cmd.arg(OFF);
+    }
+    run(&mut cmd)
+}
+
+/// macOS Proxies: Get Passive FTP Mode (PASV)
+pub fn get_passive_ftp(network: Network) -> Result<bool> {
+    let mut cmd = cmd();
+    cmd.args(["-getpassiveftp", network.as_str()]);
+    parsed(&mut cmd, |text| on_off(text, "Passive FTP"))
+}
+
+/// macOS Proxies: Web Proxy (HTTP)
+pub fn web_proxy(network: Network, setup: Config<&Address>) -> Result<()> {
+    let mut cmd = cmd();
+    match setup {
         Config::Off => {
-            cmd.args(&["-setsecurewebproxystate", network.as_str(), OFF]);
+            cmd.args(["-setwebproxystate", network.as_str(), OFF]);
         }
         Config::On => {
-            cmd.args(&["-setsecurewebproxystate", network.as_str(), ON]);
+            cmd.args(["-setwebproxystate", network.as_str(), ON]);
+        }
+        Config::Clear => {
+            return clear(&network, "-setwebproxy", &["", ""], "-setwebproxystate");
         }
         Config::Value(addr) => {
-            let mut ops = vec!["-setsecurewebproxy", network.as_str(), addr.host, addr.port];
-            if let Some((username, password)) = addr.auth {
-                ops.extend_from_slice(&[ON, username, password]);
-            }
-            cmd.args(&ops);
+            cmd.args(["-setwebproxy", network.as_str()]);
+            addr.apply(&mut cmd);
         }
     }
-    cmd.status()
+    run(&mut cmd)
 }
 
-/// macOS Proxies: Socks Proxy
-pub fn socks_proxy(network: Network, setup: Config<&Address>) -> Result<ExitStatus> {
+/// macOS Proxies: Get Web Proxy (HTTP)
+pub fn get_web_proxy(network: Network) -> Result<ProxySettings> {
+    let mut cmd = cmd();
+    cmd.args(["-getwebproxy", network.as_str()]);
+    parsed(&mut cmd, ProxySettings::parse)
+}
+
+/// macOS Proxies: Secure Web Proxy (HTTPS)
+pub fn secure_web_proxy(network: Network, setup: Config<&Address>) -> Result<()> {
     let mut cmd = cmd();
     match setup {
         Config::Off => {
-            cmd.args(&["-setsocksfirewallproxystate", network.as_str(), "\"\"","\"\""]);
-            cmd.args(&["-setsocksfirewallproxystate", network.as_str(), OFF]);
+            cmd.args(["-setsecurewebproxystate", network.as_str(), OFF]);
         }
         Config::On => {
-            cmd.args(&["-setsocksfirewallproxystate", network.as_str(), ON]);
+            cmd.args(["-setsecurewebproxystate", network.as_str(), ON]);
+        }
+        Config::Clear => {
+            return clear(
+                &network,
+                "-setsecurewebproxy",
+                &["", ""],
+                "-setsecurewebproxystate",
+            );
         }
         Config::Value(addr) => {
-            let mut ops = vec![
+            cmd.args(["-setsecurewebproxy", network.as_str()]);
+            addr.apply(&mut cmd);
+        }
+    }
+    run(&mut cmd)
+}
+
+/// macOS Proxies: Get Secure Web Proxy (HTTPS)
+pub fn get_secure_web_proxy(network: Network) -> Result<ProxySettings> {
+    let mut cmd = cmd();
+    cmd.args(["-getsecurewebproxy", network.as_str()]);
+    parsed(&mut cmd, ProxySettings::parse)
+}
+
+/// macOS Proxies: Socks Proxy
+pub fn socks_proxy(network: Network, setup: Config<&Address>) -> Result<()> {
+    let mut cmd = cmd();
+    match setup {
+        Config::On => {
+            cmd.args(["-setsocksfirewallproxystate", network.as_str(), ON]);
+        }
+        // The state flag alone does not reliably disable the socks proxy,
+        // so the stored server is wiped first
+        Config::Off | Config::Clear => {
+            return clear(
+                &network,
                 "-setsocksfirewallproxy",
-                network.as_str(),
-                addr.host,
-                addr.port,
-            ];
-            if let Some((username, password)) = addr.auth {
-                ops.extend_from_slice(&[ON, username, password]);
-            }
-            cmd.args(&ops);
+                &["", ""],
+                "-setsocksfirewallproxystate",
+            );
+        }
+        Config::Value(addr) => {
+            cmd.args(["-setsocksfirewallproxy", network.as_str()]);
+            addr.apply(&mut cmd);
+        }
+    }
+    run(&mut cmd)
+}
+
+/// macOS Proxies: Get Socks Proxy
+pub fn get_socks_proxy(network: Network) -> Result<ProxySettings> {
+    let mut cmd = cmd();
+    cmd.args(["-getsocksfirewallproxy", network.as_str()]);
+    parsed(&mut cmd, ProxySettings::parse)
+}
+
+/// macOS Proxies: Streaming Proxy (RTSP)
+pub fn streaming_proxy(network: Network, setup: Config<&Address>) -> Result<()> {
+    require(capabilities().streaming_proxy, "-setstreamingproxy")?;
+    let mut cmd = cmd();
+    match setup {
+        Config::Off => {
+            cmd.args(["-setstreamingproxystate", network.as_str(), OFF]);
+        }
+        Config::On => {
+            cmd.args(["-setstreamingproxystate", network.as_str(), ON]);
+        }
+        Config::Clear => {
+            return clear(
+                &network,
+                "-setstreamingproxy",
+                &["", ""],
+                "-setstreamingproxystate",
+            );
+        }
+        Config::Value(addr) => {
+            cmd.args(["-setstreamingproxy", network.as_str()]);
+            addr.apply(&mut cmd);
+        }
+    }
+    run(&mut cmd)
+}
+
+/// macOS Proxies: Get Streaming Proxy (RTSP)
+pub fn get_streaming_proxy(network: Network) -> Result<ProxySettings> {
+    require(capabilities().streaming_proxy, "-getstreamingproxy")?;
+    let mut cmd = cmd();
+    cmd.args(["-getstreamingproxy", network.as_str()]);
+    parsed(&mut cmd, ProxySettings::parse)
+}
+
+/// macOS Proxies: Gopher Proxy
+pub fn gopher_proxy(network: Network, setup: Config<&Address>) -> Result<()> {
+    require(capabilities().gopher_proxy, "-setgopherproxy")?;
+    let mut cmd = cmd();
+    match setup {
+        Config::Off => {
+            cmd.args(["-setgopherproxystate", network.as_str(), OFF]);
+        }
+        Config::On => {
+            cmd.args(["-setgopherproxystate", network.as_str(), ON]);
+        }
+        Config::Clear => {
+            return clear(
+                &network,
+                "-setgopherproxy",
+                &["", ""],
+                "-setgopherproxystate",
+            );
+        }
+        Config::Value(addr) => {
+            cmd.args(["-setgopherproxy", network.as_str()]);
+            addr.apply(&mut cmd);
         }
     }
-    cmd.status()
+    run(&mut cmd)
+}
+
+/// macOS Proxies: Get Gopher Proxy
+pub fn get_gopher_proxy(network: Network) -> Result<ProxySettings> {
+    require(capabilities().gopher_proxy, "-getgopherproxy")?;
+    let mut cmd = cmd();
+    cmd.args(["-getgopherproxy", network.as_str()]);
+    parsed(&mut cmd, ProxySettings::parse)
 }
 
 /// macOS Proxies: Bypass proxy settings for these Hosts & Domains
-pub fn proxy_by_pass_domain(network: Network, hosts: &[&str]) -> Result<ExitStatus> {
+pub fn proxy_by_pass_domain(network: Network, hosts: &[&str]) -> Result<()> {
+    run(&mut set_list("-setproxybypassdomains", &network, hosts))
+}
+
+/// macOS Proxies: Get bypass proxy settings for these Hosts & Domains
+pub fn get_proxy_bypass_domains(network: Network) -> Result<Vec<String>> {
     let mut cmd = cmd();
-    cmd.args(&["-setproxybypassdomains", network.as_str()]);
-    if hosts.is_empty() {
-        cmd.arg("Empty");
-    } else {
-        cmd.args(hosts);
+    cmd.args(["-getproxybypassdomains", network.as_str()]);
+    Ok(entries(&output(&mut cmd)?))
+}
+
+/// macOS Proxies: Like `proxy_by_pass_domain`, but entries are validated first and
+/// schemes such as `http://` are stripped
+pub fn checked_proxy_by_pass_domain(network: Network, hosts: &[&str]) -> Result<()> {
+    let mut valid = Vec::new();
+    let mut invalid = Vec::new();
+    for host in hosts {
+        match bypass_domain(host) {
+            Ok(host) => valid.push(host),
+            Err(reason) => invalid.push(InvalidBypassDomain {
+                entry: host.to_string(),
+                reason,
+            }),
+        }
+    }
+    if !invalid.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            InvalidBypassDomains(invalid),
+        ));
+    }
+    proxy_by_pass_domain(network, &valid)
+}
+
+/// macOS Proxies: Add Hosts & Domains to the bypass list, skipping case-insensitive duplicates
+pub fn add_proxy_bypass_domains(network: Network, hosts: &[&str]) -> Result<()> {
+    let previous = get_proxy_bypass_domains(network.clone())?;
+    let mut intended = previous.clone();
+    for host in hosts {
+        if !intended.iter().any(|item| item.eq_ignore_ascii_case(host)) {
+            intended.push(host.to_string());
+        }
     }
-    cmd.status()
+    update("-setproxybypassdomains", &network, previous, intended)
+}
+
+/// macOS Proxies: Remove Hosts & Domains from the bypass list, ignoring case
+pub fn remove_proxy_bypass_domains(network: Network, hosts: &[&str]) -> Result<()> {
+    let previous = get_proxy_bypass_domains(network.clone())?;
+    let intended = previous
+        .iter()
+        .filter(|item| !hosts.iter().any(|host| item.eq_ignore_ascii_case(host)))
+        .cloned()
+        .collect();
+    update("-setproxybypassdomains", &network, previous, intended)
 }
 
 /// macOS DNS
-pub fn dns_server(network: Network, hosts: &[&str]) -> Result<ExitStatus> {
+pub fn dns_server(network: Network, hosts: &[&str]) -> Result<()> {
+    run(&mut set_list("-setdnsservers", &network, hosts))
+}
+
+/// macOS DNS: Flush the DNS cache so new servers take effect immediately, requires root
+/// (`PermissionDenied` otherwise)
+pub fn flush_dns_cache() -> Result<()> {
+    let mut flush = Cmd::new("dscacheutil");
+    flush.arg("-flushcache");
+    let mut hup = Cmd::new("killall");
+    hup.args(["-HUP", "mDNSResponder"]);
+    for cmd in [&mut flush, &mut hup] {
+        run(cmd)?;
+    }
+    Ok(())
+}
+
+/// macOS DNS: `dns_server` followed by `flush_dns_cache`
+pub fn dns_server_and_flush(network: Network, hosts: &[&str]) -> Result<()> {
+    dns_server(network, hosts)?;
+    flush_dns_cache()
+}
+
+/// macOS DNS: Set DNS Servers from typed addresses, IPv4 and IPv6 can be mixed
+pub fn set_dns_servers(network: Network, servers: &[IpAddr]) -> Result<()> {
+    let servers = servers.iter().map(IpAddr::to_string).collect::<Vec<_>>();
+    let servers = servers.iter().map(String::as_str).collect::<Vec<_>>();
+    dns_server(network, &servers)
+}
+
+/// macOS DNS: Like `dns_server`, but every host must be an IP address.
+/// Zone-scoped IPv6 addresses such as `fe80::1%en0` are rejected
+pub fn checked_dns_server(network: Network, hosts: &[&str]) -> Result<()> {
+    let servers = hosts
+        .iter()
+        .map(|host| ip_addr(host, ErrorKind::InvalidInput))
+        .collect::<Result<Vec<_>>>()?;
+    set_dns_servers(network, &servers)
+}
+
+/// macOS DNS: Add DNS Servers to the end of the current list, skipping duplicates
+pub fn add_dns_servers(network: Network, new: &[IpAddr]) -> Result<()> {
+    let previous = get_dns_servers(network.clone())?;
+    let mut intended = previous.clone();
+    for addr in new {
+        if !intended.contains(addr) {
+            intended.push(*addr);
+        }
+    }
+    update("-setdnsservers", &network, previous, intended)
+}
+
+/// macOS DNS: Remove DNS Servers from the current list
+pub fn remove_dns_servers(network: Network, victims: &[IpAddr]) -> Result<()> {
+    let previous = get_dns_servers(network.clone())?;
+    let intended = previous
+        .iter()
+        .filter(|addr| !victims.contains(addr))
+        .copied()
+        .collect();
+    update("-setdnsservers", &network, previous, intended)
+}
+
+/// macOS DNS: Search Domains
+pub fn search_domains(network: Network, domains: &[&str]) -> Result<()> {
+    if let Some(domain) = domains
+        .iter()
+        .find(|domain| domain.is_empty() || domain.contains(char::is_whitespace))
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid search domain: {domain:?}"),
+        ));
+    }
+    run(&mut set_list("-setsearchdomains", &network, domains))
+}
+
+/// macOS DNS: Get DNS Servers
+pub fn get_dns_servers(network: Network) -> Result<Vec<IpAddr>> {
     let mut cmd = cmd();
-    cmd.args(&["-setdnsservers", network.as_str()]);
-    if hosts.is_empty() {
-        cmd.arg("Empty");
+    cmd.args(["-getdnsservers", network.as_str()]);
+    entries(&output(&mut cmd)?)
+        .into_iter()
+        .map(|entry| ip_addr(&entry, ErrorKind::InvalidData))
+        .collect()
+}
+
+/// macOS DNS: Get Search Domains
+pub fn get_search_domains(network: Network) -> Result<Vec<String>> {
+    let mut cmd = cmd();
+    cmd.args(["-getsearchdomains", network.as_str()]);
+    Ok(entries(&output(&mut cmd)?))
+}
+
+/// Hardware (MAC) address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MacAddress([u8; 6]);
+
+impl MacAddress {
+    pub fn new(octets: [u8; 6]) -> Self {
+        Self(octets)
+    }
+
+    pub fn octets(&self) -> [u8; 6] {
+        self.0
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+impl FromStr for MacAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut octets = [0; 6];
+        let mut parts = s.split(':');
+        for octet in &mut octets {
+            *octet = parts
+                .next()
+                .and_then(|part| u8::from_str_radix(part, 16).ok())
+                .ok_or_else(|| invalid_data(s))?;
+        }
+        match parts.next() {
+            Some(_) => Err(invalid_data(s)),
+            None => Ok(Self(octets)),
+        }
+    }
+}
+
+/// IPv4 configuration method
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigMethod {
+    Dhcp,
+    Manual,
+    ManualWithDhcpRouter,
+    Bootp,
+    Off,
+    Other(String),
+}
+
+/// IPv6 configuration method
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ipv6Method {
+    Automatic,
+    Manual,
+    LinkLocal,
+    Off,
+    Other(String),
+}
+
+/// TCP/IP configuration of a network service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceInfo {
+    pub method: ConfigMethod,
+    pub ip_address: Option<Ipv4Addr>,
+    pub subnet_mask: Option<Ipv4Addr>,
+    pub router: Option<Ipv4Addr>,
+    pub client_id: Option<String>,
+    pub ipv6: Ipv6Method,
+    pub ipv6_address: Option<Ipv6Addr>,
+    pub ipv6_prefix_length: Option<u8>,
+    pub ipv6_router: Option<Ipv6Addr>,
+    /// `Ethernet Address`, or `Wi-Fi ID` on Wi-Fi services
+    pub hardware_address: Option<MacAddress>,
+}
+
+impl ServiceInfo {
+    fn parse(text: &str) -> Result<Self> {
+        let method = match text
+            .lines()
+            .find(|line| line.trim().ends_with("Configuration"))
+        {
+            Some(line) if line.contains("DHCP Router") => ConfigMethod::ManualWithDhcpRouter,
+            Some(line) if line.starts_with("DHCP") => ConfigMethod::Dhcp,
+            Some(line) if line.starts_with("Manual") => ConfigMethod::Manual,
+            Some(line) if line.starts_with("BOOTP") => ConfigMethod::Bootp,
+            Some(line) => ConfigMethod::Other(line.trim().to_string()),
+            None => ConfigMethod::Off,
+        };
+        let mut info = Self {
+            method,
+            ip_address: None,
+            subnet_mask: None,
+            router: None,
+            client_id: None,
+            ipv6: Ipv6Method::Off,
+            ipv6_address: None,
+            ipv6_prefix_length: None,
+            ipv6_router: None,
+            hardware_address: None,
+        };
+        let mut known = false;
+        for (key, value) in fields(text) {
+            known |= key.starts_with("IP");
+            match key {
+                "IP address" => info.ip_address = optional(value),
+                "Subnet mask" => info.subnet_mask = optional(value),
+                "Router" => info.router = optional(value),
+                "Client ID" => info.client_id = optional(value),
+                "IPv6" => {
+                    info.ipv6 = match value {
+                        "Automatic" => Ipv6Method::Automatic,
+                        "Manual" => Ipv6Method::Manual,
+                        "Off" => Ipv6Method::Off,
+                        _ if value.to_ascii_lowercase().contains("link") => Ipv6Method::LinkLocal,
+                        _ => Ipv6Method::Other(value.to_string()),
+                    }
+                }
+                "IPv6 IP address" => info.ipv6_address = optional(value),
+                "IPv6 Prefix Length" => info.ipv6_prefix_length = optional(value),
+                "IPv6 Router" => info.ipv6_router = optional(value),
+                "Ethernet Address" | "Wi-Fi ID" => info.hardware_address = optional(value),
+                _ => {}
+            }
+        }
+        if known {
+            Ok(info)
+        } else {
+            Err(invalid_data(text))
+        }
+    }
+}
+
+/// macOS TCP/IP: Get IPv4 / IPv6 configuration of a network service
+pub fn get_info(network: Network) -> Result<ServiceInfo> {
+    let mut cmd = cmd();
+    cmd.args(["-getinfo", network.as_str()]);
+    parsed(&mut cmd, ServiceInfo::parse)
+}
+
+/// macOS TCP/IP: Configure IPv4 manually
+pub fn set_manual(
+    network: Network,
+    ip: Ipv4Addr,
+    subnet: Ipv4Addr,
+    router: Ipv4Addr,
+) -> Result<()> {
+    let mut cmd = cmd();
+    cmd.args(["-setmanual", network.as_str()]);
+    cmd.args([ip.to_string(), subnet.to_string(), router.to_string()]);
+    run(&mut cmd)
+}
+
+/// DHCP client ID
+#[derive(Debug, Clone)]
+pub enum ClientId<'a> {
+    /// Keep the currently set client ID
+    Unchanged,
+    /// Remove a previously set client ID
+    Clear,
+    Set(&'a str),
+}
+
+/// macOS TCP/IP: Configure IPv4 using DHCP
+pub fn set_dhcp(network: Network, client_id: ClientId) -> Result<()> {
+    let mut cmd = cmd();
+    cmd.args(["-setdhcp", network.as_str()]);
+    match client_id {
+        ClientId::Unchanged => {}
+        ClientId::Clear => {
+            cmd.arg("Empty");
+        }
+        ClientId::Set(id) => {
+            cmd.arg(id);
+        }
+    }
+    run(&mut cmd)
+}
+
+/// macOS TCP/IP: Configure IPv4 using BOOTP
+pub fn set_bootp(network: Network) -> Result<()> {
+    let mut cmd = cmd();
+    cmd.args(["-setbootp", network.as_str()]);
+    run(&mut cmd)
+}
+
+/// macOS TCP/IP: Configure IPv4 manually with the router from DHCP
+pub fn set_manual_with_dhcp_router(network: Network, ip: Ipv4Addr) -> Result<()> {
+    if ip.is_unspecified() || ip.is_broadcast() || ip.is_multicast() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{ip} is not a valid host address"),
+        ));
+    }
+    let mut cmd = cmd();
+    cmd.args([
+        "-setmanualwithdhcprouter",
+        network.as_str(),
+        &ip.to_string(),
+    ]);
+    run(&mut cmd)?;
+    verify(
+        ConfigMethod::ManualWithDhcpRouter,
+        get_info(network)?.method,
+    )
+}
+
+/// Additional IPv4 route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route {
+    pub dest: Ipv4Addr,
+    pub mask: Ipv4Addr,
+    pub gateway: Option<Ipv4Addr>,
+}
+
+/// macOS TCP/IP: Get additional IPv4 routes
+pub fn get_additional_routes(network: Network) -> Result<Vec<Route>> {
+    let mut cmd = cmd();
+    cmd.args(["-getadditionalroutes", network.as_str()]);
+    parsed(&mut cmd, |text| {
+        if text.trim_start().starts_with("There are no") {
+            return Ok(Vec::new());
+        }
+        let mut routes = Vec::new();
+        for line in text.lines() {
+            let mut columns = line.split_whitespace();
+            // Skip the header and anything else that is not a route
+            let dest = match columns.next().map(str::parse) {
+                Some(Ok(dest)) => dest,
+                _ => continue,
+            };
+            let mask = columns
+                .next()
+                .and_then(|mask| mask.parse().ok())
+                .ok_or_else(|| invalid_data(line))?;
+            let gateway = columns.next().and_then(optional);
+            routes.push(Route {
+                dest,
+                mask,
+                gateway,
+            });
+        }
+        Ok(routes)
+    })
+}
+
+/// macOS TCP/IP: Replace additional IPv4 routes, an empty slice removes all of them
+pub fn set_additional_routes(network: Network, routes: &[Route]) -> Result<()> {
+    let mut cmd = cmd();
+    cmd.args(["-setadditionalroutes", network.as_str()]);
+    for route in routes {
+        let gateway = route.gateway.map(|gateway| gateway.to_string());
+        cmd.args([route.dest.to_string(), route.mask.to_string()]);
+        cmd.arg(gateway.unwrap_or_default());
+    }
+    run(&mut cmd)
+}
+
+/// Additional IPv6 route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteV6 {
+    pub dest: Ipv6Addr,
+    pub prefix_len: u8,
+    pub gateway: Option<Ipv6Addr>,
+}
+
+/// IPv6 address without the zone of a link-local address (`fe80::1%en0`)
+fn ipv6_addr(value: &str) -> Option<Ipv6Addr> {
+    optional(value.split('%').next().unwrap_or(value))
+}
+
+fn check_prefix_len(prefix_len: u8) -> Result<()> {
+    if prefix_len <= 128 {
+        Ok(())
     } else {
-        cmd.args(hosts);
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid IPv6 prefix length: {prefix_len}"),
+        ))
+    }
+}
+
+/// macOS TCP/IP: Get additional IPv6 routes
+pub fn get_v6_additional_routes(network: Network) -> Result<Vec<RouteV6>> {
+    let mut cmd = cmd();
+    cmd.args(["-getv6additionalroutes", network.as_str()]);
+    parsed(&mut cmd, |text| {
+        if text.trim_start().starts_with("There are no") {
+            return Ok(Vec::new());
+        }
+        let mut routes = Vec::new();
+        for line in text.lines() {
+            let mut columns = line.split_whitespace();
+            // Skip the header and anything else that is not a route
+            let dest = match columns.next().and_then(ipv6_addr) {
+                Some(dest) => dest,
+                None => continue,
+            };
+            let prefix_len = columns
+                .next()
+                .and_then(|len| len.parse().ok())
+                .filter(|len| *len <= 128)
+                .ok_or_else(|| invalid_data(line))?;
+            let gateway = columns.next().and_then(ipv6_addr);
+            routes.push(RouteV6 {
+                dest,
+                prefix_len,
+                gateway,
+            });
+        }
+        Ok(routes)
+    })
+}
+
+/// macOS TCP/IP: Replace additional IPv6 routes, an empty slice removes all of them
+pub fn set_v6_additional_routes(network: Network, routes: &[RouteV6]) -> Result<()> {
+    for route in routes {
+        check_prefix_len(route.prefix_len)?;
+    }
+    let mut cmd = cmd();
+    cmd.args(["-setv6additionalroutes", network.as_str()]);
+    for route in routes {
+        let gateway = route.gateway.map(|gateway| gateway.to_string());
+        cmd.args([route.dest.to_string(), route.prefix_len.to_string()]);
+        cmd.arg(gateway.unwrap_or_default());
+    }
+    run(&mut cmd)
+}
+
+/// macOS TCP/IP: Turn IPv4 off.
+/// This can cut the connection, so the primary service is only changed with `force`
+pub fn set_v4_off(network: Network, force: bool) -> Result<()> {
+    if !force {
+        ensure_not_primary(&network)?;
+    }
+    let mut cmd = cmd();
+    cmd.args(["-setv4off", network.as_str()]);
+    run(&mut cmd)?;
+    verify(ConfigMethod::Off, get_info(network)?.method)
+}
+
+/// Switch the IPv6 mode and read it back
+fn set_v6(network: Network, flag: &str, method: Ipv6Method) -> Result<()> {
+    let mut cmd = cmd();
+    cmd.args([flag, network.as_str()]);
+    run(&mut cmd)?;
+    verify(method, get_info(network)?.ipv6)
+}
+
+/// macOS TCP/IP: Turn IPv6 off
+pub fn set_v6_off(network: Network) -> Result<()> {
+    set_v6(network, "-setv6off", Ipv6Method::Off)
+}
+
+/// macOS TCP/IP: Configure IPv6 automatically
+pub fn set_v6_automatic(network: Network) -> Result<()> {
+    set_v6(network, "-setv6automatic", Ipv6Method::Automatic)
+}
+
+/// macOS TCP/IP: Restrict IPv6 to link-local addresses
+pub fn set_v6_link_local(network: Network) -> Result<()> {
+    set_v6(network, "-setv6LinkLocal", Ipv6Method::LinkLocal)
+}
+
+/// macOS TCP/IP: Configure IPv6 manually
+pub fn set_v6_manual(
+    network: Network,
+    addr: Ipv6Addr,
+    prefix_len: u8,
+    router: Ipv6Addr,
+) -> Result<()> {
+    check_prefix_len(prefix_len)?;
+    let mut cmd = cmd();
+    cmd.args(["-setv6manual", network.as_str()]);
+    cmd.args([addr.to_string(), prefix_len.to_string(), router.to_string()]);
+    run(&mut cmd)
+}
+
+/// macOS TCP/IP: Configure a 6to4 service automatically
+pub fn set_6to4_automatic(service: &str) -> Result<()> {
+    require(capabilities().six_to_four, "-set6to4automatic")?;
+    let mut cmd = cmd();
+    cmd.args(["-set6to4automatic", service]);
+    run(&mut cmd)
+}
+
+/// macOS TCP/IP: Configure a 6to4 service with a relay address
+pub fn set_6to4_manual(service: &str, relay: Ipv6Addr) -> Result<()> {
+    require(capabilities().six_to_four, "-set6to4manual")?;
+    let mut cmd = cmd();
+    cmd.args(["-set6to4manual", service, &relay.to_string()]);
+    run(&mut cmd)
+}
+
+/// macOS Hardware: Get the MAC address of a hardware port ("Wi-Fi") or device ("en0")
+pub fn get_mac_address(port_or_device: &str) -> Result<MacAddress> {
+    let mut cmd = cmd();
+    cmd.args(["-getmacaddress", port_or_device]);
+    parsed(&mut cmd, |text| {
+        let mac = field(text, "Ethernet Address")
+            .and_then(|value| value.split_whitespace().next())
+            .ok_or_else(|| invalid_data(text))?;
+        mac.parse()
+    })
+}
+
+/// macOS Sharing: Get Computer Name
+pub fn computer_name() -> Result<String> {
+    let mut cmd = cmd();
+    cmd.arg("-getcomputername");
+    Ok(output(&mut cmd)?.trim_end().to_string())
+}
+
+/// macOS Sharing: Set Computer Name
+pub fn set_computer_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "computer name cannot be empty",
+        ));
+    }
+    let mut cmd = cmd();
+    cmd.args(["-setcomputername", name]);
+    run(&mut cmd)
+}
+
+/// macOS Hardware: Detect new network hardware and create a default service for it
+pub fn detect_new_hardware() -> Result<()> {
+    let mut cmd = cmd();
+    cmd.arg("-detectnewhardware");
+    run(&mut cmd)
+}
+
+/// An entry of `list_all_network_services`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceEntry {
+    pub name: String,
+    pub enabled: bool,
+}
+
+impl ServiceEntry {
+    pub fn network(&self) -> Network<'_> {
+        Network::Name(&self.name)
+    }
+}
+
+/// macOS Services: List all network services
+pub fn list_all_network_services() -> Result<Vec<ServiceEntry>> {
+    let mut cmd = cmd();
+    cmd.arg("-listallnetworkservices");
+    let text = output(&mut cmd)?;
+    // The first line explains the `*` marker of disabled services
+    Ok(text
+        .lines()
+        .skip(1)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.strip_prefix('*') {
+            Some(name) => ServiceEntry {
+                name: name.to_string(),
+                enabled: false,
+            },
+            None => ServiceEntry {
+                name: line.to_string(),
+                enabled: true,
+            },
+        })
+        .collect())
+}
+
+/// An entry of `list_network_service_order`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderedService {
+    /// Position in the service order, starting at 1
+    pub index: usize,
+    pub name: String,
+    pub hardware_port: String,
+    pub device: Option<String>,
+    pub enabled: bool,
+}
+
+impl OrderedService {
+    pub fn network(&self) -> Network<'_> {
+        Network::Name(&self.name)
+    }
+}
+
+/// macOS Services: List network services in the order they are contacted
+pub fn list_network_service_order() -> Result<Vec<OrderedService>> {
+    let mut cmd = cmd();
+    cmd.arg("-listnetworkserviceorder");
+    parsed(&mut cmd, |text| {
+        let mut services: Vec<OrderedService> = Vec::new();
+        for line in text.lines().skip(1) {
+            if let Some(port) = line
+                .strip_prefix("(Hardware Port: ")
+                .and_then(|rest| rest.strip_suffix(')'))
+            {
+                let (port, device) = port.split_once(", Device:").unwrap_or((port, ""));
+                let service = services.last_mut().ok_or_else(|| invalid_data(text))?;
+                service.hardware_port = port.to_string();
+                service.device = Some(device.trim())
+                    .filter(|device| !device.is_empty())
+                    .map(String::from);
+            } else if let Some((marker, name)) = line
+                .strip_prefix('(')
+                .and_then(|rest| rest.split_once(") "))
+            {
+                services.push(OrderedService {
+                    index: services.len() + 1,
+                    name: name.to_string(),
+                    hardware_port: String::new(),
+                    device: None,
+                    enabled: marker != "*",
+                });
+            }
+        }
+        Ok(services)
+    })
+}
+
+/// macOS Hardware: Wait until a network service exists, e.g. right after plugging in a device
+pub fn wait_for_service(network: Network, timeout: Duration) -> Result<()> {
+    let start = Instant::now();
+    loop {
+        let services = list_all_network_services()?;
+        if services.iter().any(|entry| entry.name == network.as_str()) {
+            return Ok(());
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return Err(Error::new(
+                ErrorKind::TimedOut,
+                format!("network service {:?} did not appear", network.as_str()),
+            ));
+        }
+        detect_new_hardware()?;
+        thread::sleep((timeout - elapsed).min(Duration::from_millis(500)));
+    }
+}
+
+/// An entry of `list_all_hardware_ports`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HardwarePort {
+    pub name: String,
+    pub device: String,
+    /// `None` when networksetup prints `N/A`, e.g. for VLANs
+    pub mac: Option<MacAddress>,
+}
+
+/// macOS Hardware: List all hardware ports with their device name and MAC address
+pub fn list_all_hardware_ports() -> Result<Vec<HardwarePort>> {
+    let mut cmd = cmd();
+    cmd.arg("-listallhardwareports");
+    let text = output(&mut cmd)?;
+    let mut ports: Vec<HardwarePort> = Vec::new();
+    for (key, value) in fields(&text) {
+        match (key, ports.last_mut()) {
+            ("Hardware Port", _) => ports.push(HardwarePort {
+                name: value.to_string(),
+                device: String::new(),
+                mac: None,
+            }),
+            ("Device", Some(port)) => port.device = value.to_string(),
+            ("Ethernet Address", Some(port)) => port.mac = value.parse().ok(),
+            _ => {}
+        }
+    }
+    Ok(ports)
+}
+
+fn service_names() -> Result<Vec<String>> {
+    let services = list_all_network_services()?;
+    Ok(services.into_iter().map(|entry| entry.name).collect())
+}
+
+/// Fail with `UnknownService` unless the network service exists
+fn ensure_service(network: &Network) -> Result<()> {
+    ensure_services(network, None)
+}
+
+/// Like `ensure_service`, and fail with `AlreadyExists` if `new_name` is taken
+fn ensure_services(network: &Network, new_name: Option<&str>) -> Result<()> {
+    let services = list_all_network_services()?;
+    if !services.iter().any(|entry| entry.name == network.as_str()) {
+        return Err(Error::UnknownService {
+            command: None,
+            requested: network.as_str().to_string(),
+            available: services.into_iter().map(|entry| entry.name).collect(),
+        });
+    }
+    match new_name {
+        Some(name) if services.iter().any(|entry| entry.name == name) => Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("network service {name} already exists"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Fail with `PrimaryService` if the network service is the first enabled one in the order
+fn ensure_not_primary(network: &Network) -> Result<()> {
+    let order = list_network_service_order()?;
+    match order.iter().find(|service| service.enabled) {
+        Some(primary) if primary.name == network.as_str() => {
+            Err(Error::other(PrimaryService(primary.name.clone())))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// macOS Services: Get whether a network service is enabled
+pub fn service_enabled(network: Network) -> Result<bool> {
+    let mut cmd = cmd();
+    cmd.args(["-getnetworkserviceenabled", network.as_str()]);
+    parsed(&mut cmd, |text| match text.trim() {
+        "Enabled" => Ok(true),
+        "Disabled" => Ok(false),
+        _ => Err(invalid_data(text)),
+    })
+}
+
+/// macOS Services: Enable or disable a network service
+pub fn set_service_enabled(network: Network, enable: bool) -> Result<()> {
+    ensure_service(&network)?;
+    let mut cmd = cmd();
+    cmd.args(["-setnetworkserviceenabled", network.as_str()]);
+    if enable {
+        cmd.arg(ON);
+    } else {
+        cmd.arg(OFF);
+    }
+    run(&mut cmd)
+}
+
+/// macOS Services: Create a network service on a hardware port
+pub fn create_network_service(name: &str, hardware_port: &str) -> Result<()> {
+    let mut cmd = cmd();
+    cmd.args(["-createnetworkservice", name, hardware_port]);
+    run(&mut cmd)
+}
+
+/// macOS Services: Create a network service on the hardware port of a device ("en5")
+pub fn create_network_service_on_device(device: &str, name: &str) -> Result<()> {
+    let ports = list_all_hardware_ports()?;
+    let port = ports
+        .iter()
+        .find(|port| port.device == device)
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("{device} is not a hardware port device"),
+            )
+        })?;
+    create_network_service(name, &port.name)
+}
+
+/// macOS Services: Remove a network service.
+/// The primary service (first enabled in the service order) is only removed with `force`
+pub fn remove_network_service(network: Network, force: bool) -> Result<()> {
+    if !force {
+        ensure_not_primary(&network)?;
+    }
+    let mut cmd = cmd();
+    cmd.args(["-removenetworkservice", network.as_str()]);
+    run(&mut cmd)
+}
+
+/// macOS Services: Rename a network service
+pub fn rename_network_service(network: Network, new_name: &str) -> Result<()> {
+    ensure_services(&network, Some(new_name))?;
+    let mut cmd = cmd();
+    cmd.args(["-renamenetworkservice", network.as_str(), new_name]);
+    run(&mut cmd)
+}
+
+/// macOS Services: Duplicate a network service with all of its settings
+pub fn duplicate_network_service(source: Network, new_name: &str) -> Result<()> {
+    ensure_services(&source, Some(new_name))?;
+    let mut cmd = cmd();
+    cmd.args(["-duplicatenetworkservice", source.as_str(), new_name]);
+    run(&mut cmd)
+}
+
+/// macOS Services: Set the order network services are contacted in.
+/// Every enabled service must be listed, disabled ones left out are kept at the end
+pub fn order_network_services(order: &[Network]) -> Result<()> {
+    let services = list_all_network_services()?;
+    let listed = |entry: &ServiceEntry| order.iter().any(|network| network.as_str() == entry.name);
+    let missing = services
+        .iter()
+        .filter(|entry| entry.enabled && !listed(entry))
+        .map(|entry| entry.name.clone())
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            MissingServices(missing),
+        ));
+    }
+    let mut cmd = cmd();
+    cmd.arg("-ordernetworkservices");
+    cmd.args(order.iter().map(Network::as_str));
+    cmd.args(
+        services
+            .iter()
+            .filter(|entry| !listed(entry))
+            .map(|entry| entry.name.as_str()),
+    );
+    run(&mut cmd)
+}
+
+/// Device name ("en0") of a network service, devices are accepted as they are
+fn device(network: &Network) -> Result<String> {
+    let name = network.as_str();
+    let ports = list_all_hardware_ports()?;
+    match ports
+        .iter()
+        .find(|port| port.name == name || port.device == name)
+    {
+        Some(port) => Ok(port.device.clone()),
+        None => Err(Error::new(
+            ErrorKind::NotFound,
+            format!("{name} is not a hardware port or device"),
+        )),
+    }
+}
+
+/// DHCP lease from `ipconfig getpacket`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DhcpLease {
+    pub address: Option<Ipv4Addr>,
+    pub server_identifier: Option<Ipv4Addr>,
+    pub lease_time: Option<Duration>,
+    pub subnet_mask: Option<Ipv4Addr>,
+    pub routers: Vec<Ipv4Addr>,
+    pub dns_servers: Vec<Ipv4Addr>,
+    pub domain_name: Option<String>,
+}
+
+impl DhcpLease {
+    fn parse(text: &str) -> Self {
+        let addrs = |value: &str| {
+            value
+                .trim_matches(|c| c == '{' || c == '}')
+                .split(',')
+                .filter_map(|addr| addr.trim().parse().ok())
+                .collect()
+        };
+        let mut lease = Self::default();
+        for line in text.lines() {
+            if let Some(addr) = line.strip_prefix("yiaddr = ") {
+                lease.address = optional(addr.trim());
+                continue;
+            }
+            // Options are printed as `name (type): value`
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let name = key.split_whitespace().next().unwrap_or_default();
+            let value = value.trim();
+            match name {
+                "server_identifier" => lease.server_identifier = optional(value),
+                "lease_time" => {
+                    lease.lease_time = u64::from_str_radix(value.trim_start_matches("0x"), 16)
+                        .ok()
+                        .map(Duration::from_secs);
+                }
+                "subnet_mask" => lease.subnet_mask = optional(value),
+                "router" => lease.routers = addrs(value),
+                "domain_name_server" => lease.dns_servers = addrs(value),
+                "domain_name" => lease.domain_name = optional(value),
+                _ => {}
+            }
+        }
+        lease
+    }
+}
+
+/// macOS TCP/IP: Renew the DHCP lease of a network service or device
+pub fn renew_dhcp_lease(network: Network) -> Result<()> {
+    let mut cmd = Cmd::new("ipconfig");
+    cmd.args(["set", &device(&network)?, "DHCP"]);
+    run(&mut cmd)
+}
+
+/// macOS TCP/IP: Get the DHCP lease of a network service or device
+pub fn get_dhcp_lease(network: Network) -> Result<DhcpLease> {
+    let mut cmd = Cmd::new("ipconfig");
+    cmd.args(["getpacket", &device(&network)?]);
+    Ok(DhcpLease::parse(&output(&mut cmd)?))
+}
+
+/// MTU setting of a hardware port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mtu {
+    Standard,
+    Custom(u32),
+}
+
+/// Result of `get_mtu`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MtuSetting {
+    pub active: u32,
+    pub current: Mtu,
+}
+
+impl MtuSetting {
+    fn parse(text: &str) -> Result<Self> {
+        // Active MTU: 1500 (Current Setting: 1500)
+        let value = field(text, "Active MTU").ok_or_else(|| invalid_data(text))?;
+        let (active, current) = value.split_once('(').unwrap_or((value, ""));
+        let active = active.trim().parse().map_err(|_| invalid_data(text))?;
+        let current = current
+            .trim_start_matches("Current Setting:")
+            .trim()
+            .strip_suffix(')')
+            .unwrap_or_default();
+        let current = if current.is_empty() || current.starts_with("Standard") {
+            Mtu::Standard
+        } else {
+            Mtu::Custom(current.parse().map_err(|_| invalid_data(text))?)
+        };
+        Ok(Self { active, current })
+    }
+}
+
+/// macOS Hardware: Get the MTU of a hardware port or device
+pub fn get_mtu(port: &str) -> Result<MtuSetting> {
+    let mut cmd = cmd();
+    cmd.args(["-getMTU", port]);
+    parsed(&mut cmd, MtuSetting::parse)
+}
+
+/// macOS Hardware: Get the valid MTU range of a hardware port or device
+pub fn list_valid_mtu_range(port: &str) -> Result<RangeInclusive<u32>> {
+    let mut cmd = cmd();
+    cmd.args(["-listvalidMTUrange", port]);
+    parsed(&mut cmd, |text| {
+        field(text, "Valid MTU Range")
+            .and_then(|value| value.split_once('-'))
+            .and_then(|(min, max)| Some(min.trim().parse().ok()?..=max.trim().parse().ok()?))
+            .ok_or_else(|| invalid_data(text))
+    })
+}
+
+/// macOS Hardware: Set the MTU of a hardware port or device
+pub fn set_mtu(port: &str, mtu: Mtu) -> Result<()> {
+    let value = match mtu {
+        Mtu::Standard => 1500,
+        Mtu::Custom(requested) => {
+            let range = list_valid_mtu_range(port)?;
+            if !range.contains(&requested) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    MtuOutOfRange {
+                        requested,
+                        min: *range.start(),
+                        max: *range.end(),
+                    },
+                ));
+            }
+            requested
+        }
+    };
+    let mut cmd = cmd();
+    cmd.args(["-setMTU", port, &value.to_string()]);
+    run(&mut cmd)
+}
+
+/// Media option flag
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MediaFlag {
+    FullDuplex,
+    HalfDuplex,
+    FlowControl,
+    EnergyEfficientEthernet,
+    Other(String),
+}
+
+impl MediaFlag {
+    fn as_str(&self) -> &str {
+        match self {
+            MediaFlag::FullDuplex => "full-duplex",
+            MediaFlag::HalfDuplex => "half-duplex",
+            MediaFlag::FlowControl => "flow-control",
+            MediaFlag::EnergyEfficientEthernet => "energy-efficient-ethernet",
+            MediaFlag::Other(s) => s,
+        }
+    }
+}
+
+impl From<&str> for MediaFlag {
+    fn from(s: &str) -> Self {
+        match s {
+            "full-duplex" => MediaFlag::FullDuplex,
+            "half-duplex" => MediaFlag::HalfDuplex,
+            "flow-control" => MediaFlag::FlowControl,
+            "energy-efficient-ethernet" => MediaFlag::EnergyEfficientEthernet,
+            _ => MediaFlag::Other(s.to_string()),
+        }
+    }
+}
+
+/// Media option such as `autoselect` or `1000baseT <full-duplex,flow-control>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MediaOption {
+    Autoselect,
+    Subtype {
+        subtype: String,
+        flags: Vec<MediaFlag>,
+    },
+}
+
+impl fmt::Display for MediaOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaOption::Autoselect => write!(f, "autoselect"),
+            MediaOption::Subtype { subtype, flags } if flags.is_empty() => write!(f, "{subtype}"),
+            MediaOption::Subtype { subtype, flags } => {
+                let flags = flags.iter().map(MediaFlag::as_str).collect::<Vec<_>>();
+                write!(f, "{subtype} <{}>", flags.join(","))
+            }
+        }
+    }
+}
+
+impl FromStr for MediaOption {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (subtype, flags) = match s.split_once('<') {
+            Some((subtype, flags)) => (subtype.trim(), flags.trim_end_matches('>')),
+            None => (s, ""),
+        };
+        match subtype {
+            "" => Err(invalid_data(s)),
+            "autoselect" => Ok(MediaOption::Autoselect),
+            _ => Ok(MediaOption::Subtype {
+                subtype: subtype.to_string(),
+                flags: flags
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|flag| !flag.is_empty())
+                    .map(MediaFlag::from)
+                    .collect(),
+            }),
+        }
+    }
+}
+
+/// Result of `get_media`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaSettings {
+    /// The configured option
+    pub current: MediaOption,
+    /// The negotiated option, `None` without a link
+    pub active: Option<MediaOption>,
+}
+
+/// macOS Hardware: Get the media settings of a hardware port or device
+pub fn get_media(port: &str) -> Result<MediaSettings> {
+    let mut cmd = cmd();
+    cmd.args(["-getmedia", port]);
+    parsed(&mut cmd, |text| {
+        let current = field(text, "Current").ok_or_else(|| invalid_data(text))?;
+        Ok(MediaSettings {
+            current: current.parse()?,
+            active: field(text, "Active")
+                .filter(|active| *active != "none")
+                .and_then(|active| active.parse().ok()),
+        })
+    })
+}
+
+/// macOS Hardware: List the valid media options of a hardware port or device
+pub fn list_valid_media(port: &str) -> Result<Vec<MediaOption>> {
+    let mut cmd = cmd();
+    cmd.args(["-listvalidmedia", port]);
+    output(&mut cmd)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// macOS Hardware: Set the media of a hardware port or device.
+/// The option is checked against `list_valid_media` first
+pub fn set_media(port: &str, media: MediaOption) -> Result<()> {
+    let same = |option: &MediaOption| match (option, &media) {
+        (
+            MediaOption::Subtype { subtype, flags },
+            MediaOption::Subtype {
+                subtype: requested,
+                flags: requested_flags,
+            },
+        ) => {
+            subtype == requested
+                && flags.len() == requested_flags.len()
+                && flags.iter().all(|flag| requested_flags.contains(flag))
+        }
+        (option, media) => option == media,
+    };
+    let valid = list_valid_media(port)?;
+    if !valid.iter().any(same) {
+        let valid = valid.iter().map(MediaOption::to_string).collect::<Vec<_>>();
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "{media} is not supported by {port}, valid media: {}",
+                valid.join(", ")
+            ),
+        ));
+    }
+    let mut cmd = cmd();
+    cmd.args(["-setmedia", port]);
+    match &media {
+        MediaOption::Autoselect => {
+            cmd.arg("autoselect");
+        }
+        MediaOption::Subtype { subtype, flags } => {
+            cmd.arg(subtype);
+            cmd.args(flags.iter().map(MediaFlag::as_str));
+        }
+    }
+    run(&mut cmd)
+}
+
+/// macOS Wi-Fi: Get whether the Wi-Fi radio of a device ("en0") or hardware port is on
+pub fn get_airport_power(device_or_port: &str) -> Result<bool> {
+    let mut cmd = cmd();
+    cmd.args(["-getairportpower", device_or_port]);
+    parsed(&mut cmd, |text| {
+        // Wi-Fi Power (en0): On
+        let power = fields(text).find(|(key, _)| key.ends_with(')') && key.contains("Power"));
+        match power {
+            Some((_, "On")) => Ok(true),
+            Some((_, "Off")) => Ok(false),
+            _ => Err(invalid_data(text)),
+        }
+    })
+}
+
+/// macOS Wi-Fi: Turn the Wi-Fi radio of a device ("en0") or hardware port on or off
+pub fn set_airport_power(device_or_port: &str, on: bool) -> Result<()> {
+    let mut cmd = cmd();
+    cmd.args([
+        "-setairportpower",
+        device_or_port,
+        if on { ON } else { OFF },
+    ]);
+    run(&mut cmd)?;
+    // The command can succeed even when the change is blocked
+    if get_airport_power(device_or_port)? == on {
+        Ok(())
+    } else {
+        Err(Error::other(PowerChangeRejected { requested: on }))
+    }
+}
+
+const AIRPORT: &str =
+    "/System/Library/PrivateFrameworks/Apple80211.framework/Versions/Current/Resources/airport";
+
+/// macOS Wi-Fi: Disassociate from the current Wi-Fi network and leave the radio on.
+/// Uses the `airport` utility and requires root
+pub fn disassociate_wifi(device: &str) -> Result<()> {
+    let mut cmd = Cmd::new(AIRPORT);
+    cmd.args([device, "-z"]);
+    run(&mut cmd)
+}
+
+/// macOS Wi-Fi: Get the SSID the device ("en0") is associated with
+pub fn get_airport_network(device: &str) -> Result<Option<String>> {
+    let mut cmd = cmd();
+    cmd.args(["-getairportnetwork", device]);
+    parsed(&mut cmd, |text| {
+        let line = text.lines().next().unwrap_or_default();
+        if line.starts_with("You are not associated") {
+            return Ok(None);
+        }
+        // The SSID itself may contain `: `
+        ["Current Wi-Fi Network: ", "Current AirPort Network: "]
+            .iter()
+            .find_map(|prefix| line.strip_prefix(prefix))
+            .map(|ssid| Some(ssid.to_string()))
+            .ok_or_else(|| invalid_data(text))
+    })
+}
+
+/// macOS Wi-Fi: Join a Wi-Fi network
+pub fn set_airport_network(device: &str, ssid: &str, password: Option<&str>) -> Result<()> {
+    let mut cmd = cmd();
+    cmd.args(["-setairportnetwork", device, ssid]);
+    if let Some(password) = password {
+        cmd.secret(password);
+    }
+    let text = output(&mut cmd).map_err(|err| match WifiJoinError::parse(&err.message()) {
+        Some(join) => Error::other(join),
+        None => err,
+    })?;
+    match WifiJoinError::parse(&text) {
+        Some(join) => Err(Error::other(join)),
+        None => Ok(()),
+    }
+}
+
+/// macOS Wi-Fi: Join a Wi-Fi network and wait until it is associated and has an IPv4 address
+pub fn join_wifi_and_wait(
+    device: &str,
+    ssid: &str,
+    password: Option<&str>,
+    timeout: Duration,
+) -> Result<()> {
+    let start = Instant::now();
+    set_airport_network(device, ssid, password)?;
+    let mut last = JoinTimedOut {
+        ssid: None,
+        address: None,
+    };
+    loop {
+        last.ssid = get_airport_network(device)?;
+        last.address = None;
+        if last.ssid.as_deref() == Some(ssid) {
+            let mut cmd = Cmd::new("ipconfig");
+            cmd.args(["getifaddr", device]);
+            // Fails until DHCP assigned an address
+            last.address = output(&mut cmd).ok().and_then(|addr| optional(addr.trim()));
+            if last.address.is_some() {
+                return Ok(());
+            }
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return Err(Error::new(ErrorKind::TimedOut, last));
+        }
+        thread::sleep((timeout - elapsed).min(Duration::from_millis(500)));
+    }
+}
+
+/// macOS Wi-Fi: List preferred wireless networks in join order
+pub fn list_preferred_wireless_networks(device: &str) -> Result<Vec<String>> {
+    let mut cmd = cmd();
+    cmd.args(["-listpreferredwirelessnetworks", device]);
+    parsed(&mut cmd, |text| {
+        let mut lines = text.lines();
+        match lines.next() {
+            Some(header) if header.starts_with("Preferred networks on") => {}
+            _ => return Err(invalid_data(text)),
+        }
+        // Only the single leading tab is formatting, the rest belongs to the SSID
+        Ok(lines
+            .filter_map(|line| line.strip_prefix('\t'))
+            .map(String::from)
+            .collect())
+    })
+}
+
+/// Wi-Fi security type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WifiSecurity {
+    Open,
+    Wep,
+    Wpa,
+    Wpa2,
+    Wpa3,
+    /// WPA Enterprise
+    WpaE,
+    /// WPA2 Enterprise
+    Wpa2E,
+    /// WPA3 Enterprise
+    Wpa3E,
+    /// 802.1X WEP
+    Ieee8021xWep,
+}
+
+impl WifiSecurity {
+    fn as_str(&self) -> &'static str {
+        match self {
+            WifiSecurity::Open => "OPEN",
+            WifiSecurity::Wep => "WEP",
+            WifiSecurity::Wpa => "WPA",
+            WifiSecurity::Wpa2 => "WPA2",
+            WifiSecurity::Wpa3 => "WPA3",
+            WifiSecurity::WpaE => "WPAE",
+            WifiSecurity::Wpa2E => "WPA2E",
+            WifiSecurity::Wpa3E => "WPA3E",
+            WifiSecurity::Ieee8021xWep => "8021XWEP",
+        }
+    }
+}
+
+/// Wi-Fi commands print their errors to stdout and still exit with 0
+fn wifi(cmd: &mut Cmd) -> Result<String> {
+    let output = execute(cmd)?;
+    wifi_output(cmd, output)
+}
+
+/// networksetup's own error lines, the SSID it echoes back in other messages may
+/// contain "error" too
+fn error_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("** Error") || line.starts_with("Error:"))
+}
+
+fn wifi_output(cmd: &Cmd, output: Output) -> Result<String> {
+    let text = String::from_utf8_lossy(&output.stdout).into_owned();
+    if error_lines(&text).next().is_some() {
+        Err(Error::failed(cmd.line(), output))
+    } else {
+        Ok(text)
+    }
+}
+
+/// macOS Wi-Fi: Add a preferred wireless network at an index of the join order
+pub fn add_preferred_wireless_network(
+    device: &str,
+    index: usize,
+    ssid: &str,
+    security: WifiSecurity,
+    password: Option<&str>,
+) -> Result<()> {
+    if security == WifiSecurity::Open && password.is_some() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "an open Wi-Fi network cannot have a password",
+        ));
+    }
+    let mut cmd = cmd();
+    cmd.args(["-addpreferredwirelessnetworkatindex", device, ssid]);
+    cmd.args([&index.to_string(), security.as_str()]);
+    if let Some(password) = password {
+        cmd.secret(password);
+    }
+    wifi(&mut cmd).map(|_| ())
+}
+
+/// macOS Wi-Fi: Remove a preferred wireless network
+pub fn remove_preferred_wireless_network(device: &str, ssid: &str) -> Result<()> {
+    let mut cmd = cmd();
+    cmd.args(["-removepreferredwirelessnetwork", device, ssid]);
+    let missing = |text: &str| {
+        error_lines(text).any(|line| {
+            let line = line.replace(ssid, "");
+            line.contains("not in") || line.contains("not found")
+        })
+    };
+    match wifi(&mut cmd) {
+        Err(err @ Error::Failed { .. }) if missing(&err.message()) => {
+            Err(Error::new(ErrorKind::NotFound, NotInList(ssid.to_string())))
+        }
+        result => result.map(|_| ()),
+    }
+}
+
+/// macOS Wi-Fi: Remove several preferred wireless networks, continuing past failures
+pub fn remove_preferred_wireless_networks(
+    device: &str,
+    ssids: &[&str],
+) -> Vec<(String, Result<()>)> {
+    ssids
+        .iter()
+        .map(|ssid| {
+            let result = remove_preferred_wireless_network(device, ssid);
+            (ssid.to_string(), result)
+        })
+        .collect()
+}
+
+/// macOS Wi-Fi: Remove all preferred wireless networks, returns how many were removed.
+/// With `backup` the previous list is written there first, one SSID per line
+pub fn remove_all_preferred_wireless_networks(
+    device: &str,
+    backup: Option<&Path>,
+) -> Result<usize> {
+    let ssids = list_preferred_wireless_networks(device)?;
+    if let Some(path) = backup {
+        let text = ssids
+            .iter()
+            .map(|ssid| format!("{ssid}\n"))
+            .collect::<String>();
+        fs::write(path, text)?;
+    }
+    let mut cmd = cmd();
+    cmd.args(["-removeallpreferredwirelessnetworks", device]);
+    wifi(&mut cmd)?;
+    Ok(ssids.len())
+}
+
+/// macOS Wi-Fi: Move a preferred wireless network to another index of the join order.
+///
+/// networksetup has no command for this, so the network is removed and added again
+/// with the given security type and password. If adding it at `new_index` fails it is
+/// restored at its previous index before the error is returned.
+///
+/// Without a `password` a secured network is refused before anything is removed, unless
+/// the `keychain` feature finds its saved password.
+pub fn move_preferred_wireless_network(
+    device: &str,
+    ssid: &str,
+    new_index: usize,
+    security: WifiSecurity,
+    password: Option<&str>,
+) -> Result<()> {
+    let ssids = list_preferred_wireless_networks(device)?;
+    let index = ssids
+        .iter()
+        .position(|name| name == ssid)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, NotInList(ssid.to_string())))?;
+    let password = match password {
+        Some(password) => Some(password.to_string()),
+        #[cfg(feature = "keychain")]
+        None if security != WifiSecurity::Open => keychain::wifi_password(ssid)?,
+        None => None,
+    };
+    if security != WifiSecurity::Open && password.is_none() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{ssid} needs its password to be added again"),
+        ));
+    }
+    let password = password.as_deref();
+    remove_preferred_wireless_network(device, ssid)?;
+    add_preferred_wireless_network(device, new_index, ssid, security, password).map_err(|err| {
+        match add_preferred_wireless_network(device, index, ssid, security, password) {
+            Ok(()) => err,
+            Err(restore) => Error::new(
+                err.kind(),
+                format!("{err}, restoring {ssid} at index {index} also failed: {restore}"),
+            ),
+        }
+    })
+}
+
+/// macOS Wi-Fi: Device of the Wi-Fi hardware port ("AirPort" on older systems).
+/// The first one is returned if there are several
+pub fn wifi_device() -> Result<String> {
+    let ports = list_all_hardware_ports()?;
+    ports
+        .into_iter()
+        .find(|port| port.name == "Wi-Fi" || port.name == "AirPort")
+        .map(|port| port.device)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "no Wi-Fi hardware port"))
+}
+
+/// macOS Wi-Fi: `get_airport_power` of `wifi_device`
+pub fn get_wifi_power() -> Result<bool> {
+    get_airport_power(&wifi_device()?)
+}
+
+/// macOS Wi-Fi: `set_airport_power` of `wifi_device`
+pub fn set_wifi_power(on: bool) -> Result<()> {
+    set_airport_power(&wifi_device()?, on)
+}
+
+/// macOS Wi-Fi: `get_airport_network` of `wifi_device`
+pub fn get_wifi_network() -> Result<Option<String>> {
+    get_airport_network(&wifi_device()?)
+}
+
+/// macOS Wi-Fi: `set_airport_network` of `wifi_device`
+pub fn set_wifi_network(ssid: &str, password: Option<&str>) -> Result<()> {
+    set_airport_network(&wifi_device()?, ssid, password)
+}
+
+/// macOS VLAN: List devices that can be the parent of a VLAN
+pub fn vlan_capable_devices() -> Result<Vec<String>> {
+    let mut cmd = cmd();
+    cmd.arg("-listdevicesthatsupportVLAN");
+    Ok(vlan_devices(&output(&mut cmd)?))
+}
+
+fn vlan_devices(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            // `Ethernet (en0)` or `en0`, sentences such as the no-devices message are skipped
+            match line.rsplit_once('(') {
+                Some((_, device)) => device.strip_suffix(')'),
+                None if !line.is_empty() && !line.contains(char::is_whitespace) => Some(line),
+                None => None,
+            }
+        })
+        .map(String::from)
+        .collect()
+}
+
+/// macOS VLAN: Create a VLAN on a parent device ("en0") with a tag of 1-4094
+pub fn create_vlan(name: &str, parent_device: &str, tag: u16) -> Result<()> {
+    if !(1..=4094).contains(&tag) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid VLAN tag: {tag}"),
+        ));
+    }
+    let valid = vlan_capable_devices()?;
+    if !valid.iter().any(|device| device == parent_device) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            UnsupportedVlanDevice {
+                device: parent_device.to_string(),
+                valid,
+            },
+        ));
+    }
+    let mut cmd = cmd();
+    cmd.args(["-createVLAN", name, parent_device, &tag.to_string()]);
+    run(&mut cmd)
+}
+
+/// A VLAN from `-listVLANs`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vlan {
+    /// Device name, e.g. `vlan0`
+    pub name: String,
+    pub tag: u16,
+    pub parent_device: String,
+    pub user_defined_name: Option<String>,
+}
+
+/// macOS VLAN: List VLANs
+pub fn list_vlans() -> Result<Vec<Vlan>> {
+    let mut cmd = cmd();
+    cmd.arg("-listVLANs");
+    parsed(&mut cmd, |text| {
+        if text.contains("no VLANs") || text.contains("No VLANs") {
+            return Ok(Vec::new());
+        }
+        let mut vlans: Vec<Vlan> = Vec::new();
+        for (key, value) in fields(text) {
+            match (key, vlans.last_mut()) {
+                ("VLAN User Defined Name", _) => vlans.push(Vlan {
+                    name: String::new(),
+                    tag: 0,
+                    parent_device: String::new(),
+                    user_defined_name: Some(value.to_string()).filter(|name| !name.is_empty()),
+                }),
+                ("Parent Device", Some(vlan)) => vlan.parent_device = value.to_string(),
+                (r#"Device ("Hardware" Port)"#, Some(vlan)) => vlan.name = value.to_string(),
+                ("Tag", Some(vlan)) => vlan.tag = value.parse().map_err(|_| invalid_data(text))?,
+                _ => {}
+            }
+        }
+        Ok(vlans)
+    })
+}
+
+/// macOS VLAN: Delete a VLAN, the parent device and tag must match its creation
+pub fn delete_vlan(name: &str, parent_device: &str, tag: u16) -> Result<()> {
+    let exists = list_vlans()?.iter().any(|vlan| {
+        vlan.user_defined_name.as_deref() == Some(name)
+            && vlan.parent_device == parent_device
+            && vlan.tag == tag
+    });
+    if !exists {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("VLAN {name} with tag {tag} on {parent_device} does not exist"),
+        ));
+    }
+    let mut cmd = cmd();
+    cmd.args(["-deleteVLAN", name, parent_device, &tag.to_string()]);
+    run(&mut cmd)
+}
+
+/// macOS VLAN: Delete a VLAN by its user defined name
+pub fn delete_vlan_by_name(name: &str) -> Result<()> {
+    let vlan = list_vlans()?
+        .into_iter()
+        .find(|vlan| vlan.user_defined_name.as_deref() == Some(name))
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("VLAN {name} does not exist")))?;
+    delete_vlan(name, &vlan.parent_device, vlan.tag)
+}
+
+/// A link aggregation bond
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bond {
+    /// Device name, e.g. `bond0`
+    pub device: String,
+    pub user_defined_name: Option<String>,
+    pub members: Vec<String>,
+}
+
+impl Bond {
+    fn parse(text: &str) -> Vec<Self> {
+        if text.to_ascii_lowercase().contains("no bond") {
+            return Vec::new();
+        }
+        let mut bonds: Vec<Bond> = Vec::new();
+        let mut in_members = false;
+        for line in text.lines() {
+            let indented = line.starts_with(char::is_whitespace);
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) if !indented => (key.trim().to_ascii_lowercase(), value.trim()),
+                _ => {
+                    // Member interfaces listed on the lines following `devices:`
+                    if let (true, Some(bond)) = (in_members, bonds.last_mut()) {
+                        bond.members.extend(members(line));
+                    }
+                    continue;
+                }
+            };
+            in_members = false;
+            if key.starts_with("interface") {
+                bonds.push(Bond {
+                    device: value.to_string(),
+                    user_defined_name: None,
+                    members: Vec::new(),
+                });
+            } else if let Some(bond) = bonds.last_mut() {
+                if key.starts_with("user") {
+                    bond.user_defined_name =
+                        Some(value.to_string()).filter(|name| !name.is_empty());
+                } else if key.starts_with("devices") || key.starts_with("members") {
+                    in_members = true;
+                    bond.members.extend(members(value));
+                }
+            }
+        }
+        bonds
+    }
+}
+
+/// Comma separated member devices
+fn members(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|member| !member.is_empty())
+        .map(String::from)
+}
+
+/// macOS Bond: List link aggregation bonds
+pub fn list_bonds() -> Result<Vec<Bond>> {
+    let mut cmd = cmd();
+    cmd.arg("-listBonds");
+    Ok(Bond::parse(&output(&mut cmd)?))
+}
+
+/// macOS Bond: Create a link aggregation bond, returns the bond device such as `bond0`
+pub fn create_bond(name: &str, devices: &[&str]) -> Result<String> {
+    if devices.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "a bond needs at least one member device",
+        ));
+    }
+    let previous = list_bonds()?;
+    for bond in &previous {
+        if let Some(device) = devices
+            .iter()
+            .find(|device| bond.members.iter().any(|m| m == *device))
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                BondMemberInUse {
+                    device: device.to_string(),
+                    bond: bond.device.clone(),
+                },
+            ));
+        }
+    }
+    let mut cmd = cmd();
+    cmd.args(["-createBond", name]).args(devices);
+    run(&mut cmd)?;
+    // The new device is the one that was not there before
+    list_bonds()?
+        .into_iter()
+        .map(|bond| bond.device)
+        .find(|device| !previous.iter().any(|bond| &bond.device == device))
+        .ok_or_else(|| Error::other(format!("bond {name} was not created")))
+}
+
+/// macOS Bond: Delete a bond device such as `bond0`
+pub fn delete_bond(bond_device: &str) -> Result<()> {
+    let mut cmd = cmd();
+    cmd.args(["-deleteBond", bond_device]);
+    run(&mut cmd)
+}
+
+/// A member row of `bond_status`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BondMemberStatus {
+    pub interface: String,
+    /// LACP state, e.g. `Active` or `Standby`
+    pub lacp_state: String,
+    /// Link speed and duplex, e.g. `1000baseT <full-duplex>`
+    pub link: String,
+}
+
+/// Result of `bond_status`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BondStatus {
+    pub status: String,
+    pub members: Vec<BondMemberStatus>,
+}
+
+impl BondStatus {
+    fn parse(text: &str) -> Result<Self> {
+        let status = fields(text)
+            .find(|(key, value)| key.to_ascii_lowercase().contains("status") && !value.is_empty())
+            .map(|(_, value)| value.to_string())
+            .ok_or_else(|| invalid_data(text))?;
+        let is_device = |name: &str| {
+            let digits = name.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+            digits.len() < name.len()
+                && !digits.is_empty()
+                && digits.chars().all(|c| c.is_ascii_digit())
+        };
+        // Members are tab separated rows: interface, LACP state, link
+        let members = text
+            .lines()
+            .map(|line| {
+                line.split('\t')
+                    .map(str::trim)
+                    .filter(|column| !column.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|columns| columns.len() >= 2 && is_device(columns[0]))
+            .map(|columns| BondMemberStatus {
+                interface: columns[0].to_string(),
+                lacp_state: columns[1].to_string(),
+                link: columns[2..].join(" "),
+            })
+            .collect();
+        Ok(Self { status, members })
+    }
+}
+
+/// macOS Bond: Get the status of a bond and its members
+pub fn bond_status(bond_device: &str) -> Result<BondStatus> {
+    let mut cmd = cmd();
+    cmd.args(["-showBondStatus", bond_device]);
+    parsed(&mut cmd, BondStatus::parse)
+}
+
+/// macOS PPPoE: List PPPoE services
+pub fn list_pppoe_services() -> Result<Vec<String>> {
+    let mut cmd = cmd();
+    cmd.arg("-listpppoeservices");
+    Ok(entries(&output(&mut cmd)?))
+}
+
+/// Fail with `NotFound` unless the PPPoE service exists
+fn ensure_pppoe_service(service: &str) -> Result<()> {
+    if list_pppoe_services()?.iter().any(|name| name == service) {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::NotFound,
+            format!("{service} is not a PPPoE service"),
+        ))
+    }
+}
+
+/// macOS PPPoE: Create a PPPoE service on a device ("en0")
+pub fn create_pppoe_service(
+    device: &str,
+    service_name: &str,
+    account: &str,
+    password: &str,
+    pppoe_name: Option<&str>,
+) -> Result<()> {
+    let mut cmd = cmd();
+    cmd.args(["-createpppoeservice", device, service_name, account]);
+    cmd.secret(password);
+    cmd.args(pppoe_name);
+    run(&mut cmd)
+}
+
+/// macOS PPPoE: Delete a PPPoE service
+pub fn delete_pppoe_service(service_name: &str) -> Result<()> {
+    ensure_pppoe_service(service_name)?;
+    let mut cmd = cmd();
+    cmd.args(["-deletepppoeservice", service_name]);
+    run(&mut cmd)
+}
+
+/// macOS PPPoE: Get the account name of a PPPoE service
+pub fn get_pppoe_account_name(service: &str) -> Result<String> {
+    let mut cmd = cmd();
+    cmd.args(["-getpppoeaccountname", service]);
+    Ok(output(&mut cmd)?.trim().to_string())
+}
+
+/// macOS PPPoE: Set the account name of a PPPoE service
+pub fn set_pppoe_account_name(service: &str, account: &str) -> Result<()> {
+    let mut cmd = cmd();
+    cmd.args(["-setpppoeaccountname", service, account]);
+    run(&mut cmd)?;
+    verify(account, get_pppoe_account_name(service)?.as_str())
+}
+
+/// macOS PPPoE: Set the password of a PPPoE service.
+///
+/// networksetup only accepts the password as an argument, so it is visible in the
+/// process list while the command runs. With the `secrecy` feature `password` can be a
+/// `secrecy::SecretString`.
+pub fn set_pppoe_password(service: &str, password: impl Into<Secret>) -> Result<()> {
+    let password = password.into();
+    ensure_pppoe_service(service)?;
+    let mut cmd = cmd();
+    cmd.args(["-setpppoepassword", service]);
+    cmd.secret(password.expose());
+    run(&mut cmd)
+}
+
+/// macOS PPPoE: Connect a PPPoE service, this returns before the session is up
+pub fn connect_pppoe(service: &str) -> Result<()> {
+    ensure_pppoe_service(service)?;
+    let mut cmd = cmd();
+    cmd.args(["-connectpppoeservice", service]);
+    run(&mut cmd)
+}
+
+/// macOS PPPoE: Disconnect a PPPoE service
+pub fn disconnect_pppoe(service: &str) -> Result<()> {
+    ensure_pppoe_service(service)?;
+    let mut cmd = cmd();
+    cmd.args(["-disconnectpppoeservice", service]);
+    run(&mut cmd)
+}
+
+/// PPPoE session state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PppoeStatus {
+    Connected,
+    Disconnected,
+    Connecting,
+    Disconnecting,
+    Other(String),
+}
+
+/// macOS PPPoE: Get the session state of a PPPoE service
+pub fn pppoe_status(service: &str) -> Result<PppoeStatus> {
+    let mut cmd = cmd();
+    cmd.args(["-showpppoestatus", service]);
+    let mut text = output(&mut cmd)?;
+    Ok(match text.trim() {
+        "connected" => PppoeStatus::Connected,
+        "disconnected" => PppoeStatus::Disconnected,
+        "connecting" => PppoeStatus::Connecting,
+        "disconnecting" => PppoeStatus::Disconnecting,
+        _ => {
+            text.truncate(text.trim_end().len());
+            PppoeStatus::Other(text)
+        }
+    })
+}
+
+/// macOS PPPoE: Connect a PPPoE service and wait until the session is up
+pub fn connect_pppoe_and_wait(
+    service: &str,
+    timeout: Duration,
+    poll_interval: Duration,
+) -> Result<()> {
+    let start = Instant::now();
+    connect_pppoe(service)?;
+    let mut connecting = false;
+    loop {
+        let status = pppoe_status(service)?;
+        match status {
+            PppoeStatus::Connected => return Ok(()),
+            PppoeStatus::Connecting => connecting = true,
+            PppoeStatus::Disconnected if connecting => {
+                return Err(Error::other(PppoeConnectError::AuthenticationLikelyFailed));
+            }
+            _ => {}
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return Err(Error::new(
+                ErrorKind::TimedOut,
+                PppoeConnectError::TimedOut(status),
+            ));
+        }
+        thread::sleep((timeout - elapsed).min(poll_interval));
+    }
+}
+
+/// macOS Locations: List locations
+pub fn list_locations() -> Result<Vec<String>> {
+    let mut cmd = cmd();
+    cmd.arg("-listlocations");
+    Ok(output(&mut cmd)?
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// macOS Locations: Get the current location
+pub fn current_location() -> Result<String> {
+    let mut cmd = cmd();
+    cmd.arg("-getcurrentlocation");
+    let mut name = output(&mut cmd)?;
+    name.truncate(name.trim_end_matches(['\r', '\n']).len());
+    Ok(name)
+}
+
+/// macOS Locations: Create a location, `populate` copies the default network services into it
+pub fn create_location(name: &str, populate: bool) -> Result<()> {
+    if list_locations()?.iter().any(|location| location == name) {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("location {name} already exists"),
+        ));
+    }
+    let mut cmd = cmd();
+    cmd.args(["-createlocation", name]);
+    if populate {
+        cmd.arg("populate");
+    }
+    run(&mut cmd)
+}
+
+/// macOS Locations: Delete a location.
+/// The current location is only deleted with `force`, after switching to another one
+/// ("Automatic" if it exists). It is never deleted if it is the only location
+pub fn delete_location(name: &str, force: bool) -> Result<()> {
+    if current_location()? == name {
+        if !force {
+            return Err(Error::other(ActiveLocation(name.to_string())));
+        }
+        let others: Vec<String> = list_locations()?
+            .into_iter()
+            .filter(|location| location != name)
+            .collect();
+        let fallback = others
+            .iter()
+            .find(|location| *location == "Automatic")
+            .or(others.first())
+            .ok_or_else(|| Error::other(OnlyLocation(name.to_string())))?;
+        switch_to_location(fallback)?;
+    }
+    let mut cmd = cmd();
+    cmd.args(["-deletelocation", name]);
+    run(&mut cmd)
+}
+
+/// macOS Locations: Switch to a location, verified by reading the current location back
+pub fn switch_to_location(name: &str) -> Result<()> {
+    let mut cmd = cmd();
+    cmd.args(["-switchtolocation", name]);
+    let output = execute(&mut cmd)?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    // Success prints "found it!", failures are printed to stdout as well
+    if !text.contains("found it") && !text.trim().is_empty() {
+        return Err(Error::failed(cmd.line(), output));
+    }
+    verify(name, current_location()?.as_str())
+}
+
+/// Settings applied and failed by `clone_location_settings`, per network service
+#[derive(Debug, Default)]
+pub struct LocationReport {
+    pub applied: Vec<(String, &'static str)>,
+    pub failed: Vec<(String, &'static str, Error)>,
+}
+
+impl LocationReport {
+    fn record(&mut self, service: &str, setting: &'static str, result: Result<()>) {
+        match result {
+            Ok(()) => self.applied.push((service.to_string(), setting)),
+            Err(err) => self.failed.push((service.to_string(), setting, err)),
+        }
+    }
+}
+
+type ProxyGetter = fn(Network) -> Result<ProxySettings>;
+type ProxySetter = fn(Network, Config<&Address>) -> Result<()>;
+
+/// A proxy copied by `clone_location_settings`, `state` turns it on or off without
+/// touching the server (`Config::Off` clears a SOCKS proxy)
+struct ProxyKind {
+    setting: &'static str,
+    get: ProxyGetter,
+    set: ProxySetter,
+    state: &'static str,
+}
+
+const PROXIES: [ProxyKind; 6] = [
+    ProxyKind {
+        setting: "ftp proxy",
+        get: get_ftp_proxy,
+        set: ftp_proxy,
+        state: "-setftpproxystate",
+    },
+    ProxyKind {
+        setting: "web proxy",
+        get: get_web_proxy,
+        set: web_proxy,
+        state: "-setwebproxystate",
+    },
+    ProxyKind {
+        setting: "secure web proxy",
+        get: get_secure_web_proxy,
+        set: secure_web_proxy,
+        state: "-setsecurewebproxystate",
+    },
+    ProxyKind {
+        setting: "socks proxy",
+        get: get_socks_proxy,
+        set: socks_proxy,
+        state: "-setsocksfirewallproxystate",
+    },
+    ProxyKind {
+        setting: "streaming proxy",
+        get: get_streaming_proxy,
+        set: streaming_proxy,
+        state: "-setstreamingproxystate",
+    },
+    ProxyKind {
+        setting: "gopher proxy",
+        get: get_gopher_proxy,
+        set: gopher_proxy,
+        state: "-setgopherproxystate",
+    },
+];
+
+/// Settings of a network service captured by `clone_location_settings`
+struct ServiceSnapshot {
+    name: String,
+    proxies: Vec<(&'static ProxyKind, Result<ProxySettings>)>,
+    auto_proxy: Result<AutoProxySettings>,
+    bypass_domains: Result<Vec<String>>,
+    dns_servers: Result<Vec<IpAddr>>,
+    search_domains: Result<Vec<String>>,
+    info: Result<ServiceInfo>,
+}
+
+impl ServiceSnapshot {
+    fn capture(name: String) -> Self {
+        let network = Network::Name(&name);
+        Self {
+            proxies: PROXIES
+                .iter()
+                .map(|kind| (kind, (kind.get)(network.clone())))
+                .collect(),
+            auto_proxy: get_auto_proxy(network.clone()),
+            bypass_domains: get_proxy_bypass_domains(network.clone()),
+            dns_servers: get_dns_servers(network.clone()),
+            search_domains: get_search_domains(network.clone()),
+            info: get_info(network),
+            name,
+        }
+    }
+
+    fn replay(self, report: &mut LocationReport) {
+        let name = self.name.as_str();
+        let network = || Network::Name(name);
+        for (kind, proxy) in self.proxies {
+            let result = match proxy {
+                // Removed from this version of macOS, there is nothing to copy
+                Err(err) if err.kind() == ErrorKind::Unsupported => continue,
+                proxy => proxy.and_then(|proxy| {
+                    if proxy.is_configured() {
+                        let port = proxy.port.to_string();
+                        let addr = Address::new(&proxy.host, &port);
+                        (kind.set)(network(), Config::Value(&addr))?;
+                    }
+                    let mut cmd = cmd();
+                    cmd.args([kind.state, name, if proxy.enabled { ON } else { OFF }]);
+                    run(&mut cmd)
+                }),
+            };
+            report.record(name, kind.setting, result);
+        }
+        let result = self.auto_proxy.and_then(|auto| {
+            if let Some(url) = &auto.url {
+                auto_proxy(network(), Config::Value(url))?;
+            }
+            auto_proxy(
+                network(),
+                if auto.enabled {
+                    Config::On
+                } else {
+                    Config::Off
+                },
+            )
+        });
+        report.record(name, "automatic proxy", result);
+        let result = self.bypass_domains.and_then(|domains| {
+            let domains = domains.iter().map(String::as_str).collect::<Vec<_>>();
+            proxy_by_pass_domain(network(), &domains)
+        });
+        report.record(name, "bypass domains", result);
+        let result = self
+            .dns_servers
+            .and_then(|servers| set_dns_servers(network(), &servers));
+        report.record(name, "dns servers", result);
+        let result = self.search_domains.and_then(|domains| {
+            let domains = domains.iter().map(String::as_str).collect::<Vec<_>>();
+            search_domains(network(), &domains)
+        });
+        report.record(name, "search domains", result);
+        let info = match self.info {
+            Ok(info) => info,
+            Err(err) => return report.record(name, "tcp/ip", Err(err)),
+        };
+        let result = match (info.method, info.ip_address, info.subnet_mask, info.router) {
+            (ConfigMethod::Dhcp, ..) => {
+                let client_id = info.client_id.as_deref();
+                set_dhcp(
+                    network(),
+                    client_id.map_or(ClientId::Unchanged, ClientId::Set),
+                )
+            }
+            (ConfigMethod::Manual, Some(ip), Some(subnet), Some(router)) => {
+                set_manual(network(), ip, subnet, router)
+            }
+            (ConfigMethod::ManualWithDhcpRouter, Some(ip), ..) => {
+                set_manual_with_dhcp_router(network(), ip)
+            }
+            (ConfigMethod::Bootp, ..) => set_bootp(network()),
+            (ConfigMethod::Off, ..) => set_v4_off(network(), true),
+            (method, ..) => Err(Error::other(format!(
+                "cannot apply IPv4 configuration {method:?}"
+            ))),
+        };
+        report.record(name, "ipv4", result);
+        let result = match (
+            info.ipv6,
+            info.ipv6_address,
+            info.ipv6_prefix_length,
+            info.ipv6_router,
+        ) {
+            (Ipv6Method::Automatic, ..) => set_v6_automatic(network()),
+            (Ipv6Method::Off, ..) => set_v6_off(network()),
+            (Ipv6Method::LinkLocal, ..) => set_v6_link_local(network()),
+            (Ipv6Method::Manual, Some(addr), Some(prefix_len), Some(router)) => {
+                set_v6_manual(network(), addr, prefix_len, router)
+            }
+            (method, ..) => Err(Error::other(format!(
+                "cannot apply IPv6 configuration {method:?}"
+            ))),
+        };
+        report.record(name, "ipv6", result);
+    }
+}
+
+/// macOS Locations: Create location `to` with the proxy, DNS, search domain and TCP/IP
+/// settings of every network service in location `from`.
+///
+/// This switches to `from` to read the settings, creates and switches to `to` to apply
+/// them, and always switches back to the location that was active before.
+/// Proxy passwords cannot be read and are not copied.
+pub fn clone_location_settings(from: &str, to: &str) -> Result<LocationReport> {
+    let original = current_location()?;
+    let result = clone_location(from, to);
+    let restored = switch_to_location(&original);
+    let report = result?;
+    restored?;
+    Ok(report)
+}
+
+fn clone_location(from: &str, to: &str) -> Result<LocationReport> {
+    switch_to_location(from)?;
+    let snapshots = list_all_network_services()?
+        .into_iter()
+        .map(|entry| ServiceSnapshot::capture(entry.name))
+        .collect::<Vec<_>>();
+    create_location(to, true)?;
+    switch_to_location(to)?;
+    let mut report = LocationReport::default();
+    for snapshot in snapshots {
+        snapshot.replay(&mut report);
+    }
+    Ok(report)
+}
+
+/// macOS 802.1X: List the login profiles of a network service
+pub fn list_login_profiles(network: Network) -> Result<Vec<String>> {
+    let mut cmd = cmd();
+    cmd.args(["-listloginprofiles", network.as_str()]);
+    let text = output(&mut cmd)?;
+    if text.to_ascii_lowercase().contains("no login profiles") {
+        return Ok(Vec::new());
+    }
+    // Skip the `... profiles:` header
+    Ok(entries(&text)
+        .into_iter()
+        .filter(|line| !line.ends_with(':'))
+        .collect())
+}
+
+/// 802.1X profile, login and user profiles are selected by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileScope<'a> {
+    System,
+    Login(&'a str),
+    User(&'a str),
+}
+
+impl<'a> ProfileScope<'a> {
+    /// `-<action><scope>profile <service> [profile]`
+    fn command(&self, action: &str, network: &Network<'a>) -> Cmd {
+        let (scope, profile) = match *self {
+            ProfileScope::System => ("system", None),
+            ProfileScope::Login(profile) => ("login", Some(profile)),
+            ProfileScope::User(profile) => ("user", Some(profile)),
+        };
+        let mut cmd = cmd();
+        cmd.args([
+            format!("-{action}{scope}profile").as_str(),
+            network.as_str(),
+        ]);
+        cmd.args(profile);
+        cmd
+    }
+}
+
+/// macOS 802.1X: Enable or disable a profile, this requires admin rights
+pub fn set_8021x_profile_state(network: Network, scope: ProfileScope, enabled: bool) -> Result<()> {
+    let action = if enabled { "enable" } else { "disable" };
+    run(&mut scope.command(action, &network))
+}
+
+/// Absolute path of a file that exists and can be read
+fn readable(path: &Path) -> Result<PathBuf> {
+    fs::File::open(path)
+        .and_then(|_| fs::canonicalize(path))
+        .map_err(|err| Error::new(err.kind(), format!("{}: {err}", path.display())))
+}
+
+/// macOS 802.1X: Import profiles from an XML file
+pub fn import_8021x_profiles(network: Network, path: &Path) -> Result<()> {
+    let path = readable(path)?;
+    let mut cmd = cmd();
+    cmd.args(["-import8021xProfiles", network.as_str()]);
+    cmd.arg(path);
+    run(&mut cmd)
+}
+
+/// macOS 802.1X: Export profiles to an XML file, `include_user` adds user profiles.
+/// The profiles are written next to `path` first, an existing file is only replaced
+/// once the export succeeded and wrote something
+pub fn export_8021x_profiles(network: Network, path: &Path, include_user: bool) -> Result<()> {
+    let path = std::path::absolute(path)?;
+    let name = path
+        .file_name()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "the export path has no file name"))?;
+    let mut temp = std::ffi::OsString::from(".");
+    temp.push(name);
+    temp.push(format!(".{}.tmp", std::process::id()));
+    let temp = path.with_file_name(temp);
+    let _ = fs::remove_file(&temp);
+    let mut cmd = cmd();
+    cmd.args(["-export8021xProfiles", network.as_str()]);
+    cmd.arg(&temp);
+    cmd.arg(if include_user { "yes" } else { "no" });
+    let result = run(&mut cmd).and_then(|()| {
+        // networksetup succeeds without writing anything when there are no profiles
+        match fs::metadata(&temp) {
+            Ok(meta) if meta.len() > 0 => Ok(fs::rename(&temp, &path)?),
+            _ => Err(Error::new(
+                ErrorKind::NotFound,
+                NoProfilesToExport(network.as_str().to_string()),
+            )),
+        }
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// macOS 802.1X: Set the TLS identity of a system or user profile from a PKCS#12 file
+pub fn set_8021x_tls_identity(
+    network: Network,
+    scope: ProfileScope,
+    identity_file: &Path,
+    passphrase: impl Into<Secret>,
+) -> Result<()> {
+    let passphrase = passphrase.into();
+    let path = readable(identity_file)?;
+    let mut cmd = cmd();
+    match scope {
+        ProfileScope::System => {
+            cmd.args(["-settlsidentityonsystemprofile", network.as_str()]);
+        }
+        ProfileScope::User(profile) => {
+            cmd.args(["-settlsidentityonuserprofile", network.as_str(), profile]);
+        }
+        ProfileScope::Login(_) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "login profiles have no TLS identity",
+            ));
+        }
+    }
+    cmd.arg(path).secret(passphrase.expose());
+    run(&mut cmd)
+}
+
+/// macOS 802.1X: Delete a profile, `NotFound` if it does not exist
+pub fn delete_8021x_profile(network: Network, scope: ProfileScope) -> Result<()> {
+    if let ProfileScope::Login(profile) = scope {
+        if !list_login_profiles(network.clone())?
+            .iter()
+            .any(|name| name == profile)
+        {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("login profile {profile} does not exist"),
+            ));
+        }
+    }
+    run(&mut scope.command("delete", &network)).map_err(|err| {
+        let msg = err.message().to_ascii_lowercase();
+        if msg.contains("not found") || msg.contains("does not exist") || msg.contains("no such") {
+            Error::new(ErrorKind::NotFound, err.to_string())
+        } else {
+            err
+        }
+    })
+}
+
+/// Version of networksetup or macOS, missing components are 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for Version {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.trim().split('.').map(str::parse::<u32>);
+        let mut next = || parts.next().transpose().map_err(|_| invalid_data(s));
+        let major = next()?.ok_or_else(|| invalid_data(s))?;
+        Ok(Self::new(major, next()?.unwrap_or(0), next()?.unwrap_or(0)))
+    }
+}
+
+/// macOS: Get the version of networksetup
+pub fn networksetup_version() -> Result<Version> {
+    let mut cmd = cmd();
+    cmd.arg("-version");
+    parsed(&mut cmd, |text| {
+        // networksetup, version 1.8.6
+        text.split_whitespace()
+            .find_map(|word| word.parse().ok())
+            .ok_or_else(|| invalid_data(text))
+    })
+}
+
+/// macOS: Get the version of macOS
+pub fn macos_version() -> Result<Version> {
+    let mut cmd = Cmd::new("sw_vers");
+    cmd.arg("-productVersion");
+    parsed(&mut cmd, str::parse)
+}
+
+/// networksetup features that are not available on every version of macOS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    pub ftp_proxy: bool,
+    pub streaming_proxy: bool,
+    pub gopher_proxy: bool,
+    pub six_to_four: bool,
+}
+
+impl Capabilities {
+    /// Features of a macOS version, these were removed in macOS 13 (Ventura)
+    pub fn for_macos(version: Version) -> Self {
+        let legacy = version.major < 13;
+        Self {
+            ftp_proxy: legacy,
+            streaming_proxy: legacy,
+            gopher_proxy: legacy,
+            six_to_four: legacy,
+        }
+    }
+
+    /// Features of the running system
+    pub fn detect() -> Result<Self> {
+        macos_version().map(Self::for_macos)
+    }
+}
+
+/// Detected once, everything is assumed to be supported if detection fails
+fn capabilities() -> Capabilities {
+    static CAPABILITIES: OnceLock<Capabilities> = OnceLock::new();
+    *CAPABILITIES.get_or_init(|| {
+        Capabilities::detect().unwrap_or(Capabilities {
+            ftp_proxy: true,
+            streaming_proxy: true,
+            gopher_proxy: true,
+            six_to_four: true,
+        })
+    })
+}
+
+/// Subcommands listed by `networksetup -printcommands`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupportedCommands(HashSet<String>);
+
+impl SupportedCommands {
+    fn parse(text: &str) -> Self {
+        let commands = text
+            .split_whitespace()
+            .filter_map(|word| word.strip_prefix('-'))
+            .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric()))
+            .map(|name| format!("-{name}"))
+            .collect();
+        Self(commands)
+    }
+
+    /// Accepts the flag with or without the leading `-`
+    pub fn supports(&self, command: &str) -> bool {
+        let name = command.trim_start_matches('-');
+        self.0.contains(&format!("-{name}"))
+    }
+
+    pub fn commands(&self) -> &HashSet<String> {
+        &self.0
+    }
+}
+
+/// macOS: List the subcommands networksetup supports, probed once and cached.
+/// A failed probe is cached too and not run again
+pub fn supported_commands() -> Result<&'static SupportedCommands> {
+    static COMMANDS: OnceLock<Option<SupportedCommands>> = OnceLock::new();
+    cached(&COMMANDS, probe_commands)
+}
+
+fn cached(
+    cell: &OnceLock<Option<SupportedCommands>>,
+    probe: impl FnOnce() -> Result<SupportedCommands>,
+) -> Result<&SupportedCommands> {
+    let mut failure = None;
+    let commands = cell.get_or_init(|| probe().map_err(|err| failure = Some(err)).ok());
+    match (commands, failure) {
+        (Some(commands), _) => Ok(commands),
+        (None, Some(err)) => Err(err),
+        (None, None) => Err(Error::other(
+            "listing the networksetup subcommands failed before",
+        )),
+    }
+}
+
+fn probe_commands() -> Result<SupportedCommands> {
+    let mut cmd = cmd();
+    cmd.arg("-printcommands");
+    let commands = match output(&mut cmd) {
+        Ok(text) => SupportedCommands::parse(&text),
+        Err(_) => {
+            // -help prints usage and may exit non-zero
+            let output = crate::cmd().arg("-help").output()?;
+            SupportedCommands::parse(&String::from_utf8_lossy(&output.stdout))
+        }
+    };
+    if commands.0.is_empty() {
+        return Err(invalid_data("no subcommands listed"));
+    }
+    Ok(commands)
+}
+
+/// Prefers the probed subcommands and falls back to the version table
+fn require(supported: bool, command: &str) -> Result<()> {
+    let supported = supported_commands().map_or(supported, |commands| commands.supports(command));
+    if supported {
+        Ok(())
+    } else {
+        Err(Error::Unsupported {
+            command: command.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+    use std::rc::Rc;
+
+    /// Output of a command that exited with `code`
+    fn reply(code: i32, stdout: &str, stderr: &str) -> Output {
+        Output {
+            status: ExitStatus::from_raw(code << 8),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    fn ok(stdout: &str) -> Output {
+        reply(0, stdout, "")
+    }
+
+    /// Run `f` answering its commands with `replies` in order, and return the arguments
+    /// of every command it ran
+    fn faked<T>(replies: Vec<Output>, f: impl FnOnce() -> T) -> (T, Vec<Vec<String>>) {
+        let ran = Rc::new(RefCell::new(Vec::new()));
+        let mut replies = replies.into_iter();
+        let log = Rc::clone(&ran);
+        let result = command::fake(
+            move |line| {
+                // The version and subcommand probes are cached for the whole process,
+                // answer them the same way in every test and leave them out of the log
+                if line.program == "sw_vers" {
+                    return ok("12.7.1\n");
+                }
+                if line.args == ["-printcommands"] {
+                    return ok(PRINTCOMMANDS);
+                }
+                log.borrow_mut().push(line.args.clone());
+                replies
+                    .next()
+                    .unwrap_or_else(|| panic!("unexpected command {line}"))
+            },
+            f,
+        );
+        let ran = ran.take();
+        (result, ran)
+    }
+
+    const PRINTCOMMANDS: &str = "networksetup -listallnetworkservices\n\
+        networksetup -setsecurewebproxy <networkservice> <domain> <port number>\n\
+        networksetup -setstreamingproxy <networkservice> <domain> <port number>\n\
+        networksetup -set6to4automatic <networkservice>\n\
+        networksetup -set6to4manual <networkservice> <relayaddress>\n";
+
+    const SERVICES: &str = "An asterisk (*) denotes that a network service is disabled.\n\
+        Ethernet\n\
+        Wi-Fi\n\
+        *Thunderbolt Bridge\n";
+
+    #[test]
+    fn proxy_settings_enabled() {
+        let text =
+            "Enabled: Yes\nServer: proxy.example.com\nPort: 8443\nAuthenticated Proxy Enabled: 1\n";
+        assert_eq!(
+            ProxySettings::parse(text).unwrap(),
+            ProxySettings {
+                enabled: true,
+                host: "proxy.example.com".to_string(),
+                port: 8443,
+                authenticated: true,
+            }
+        );
+    }
+
+    #[test]
+    fn proxy_settings_disabled_and_empty() {
+        let text = "Enabled: No\nServer: \nPort: 0\nAuthenticated Proxy Enabled: 0\n";
+        let settings = ProxySettings::parse(text).unwrap();
+        assert_eq!(
+            settings,
+            ProxySettings {
+                enabled: false,
+                host: String::new(),
+                port: 0,
+                authenticated: false,
+            }
+        );
+        assert!(!settings.is_configured());
+    }
+
+    #[test]
+    fn proxy_settings_rejects_garbage() {
+        assert!(ProxySettings::parse("** Error: The parameters were not valid.\n").is_err());
+        assert!(ProxySettings::parse("Enabled: Yes\nServer: a\nPort: http\n").is_err());
+    }
+
+    #[test]
+    fn parse_error_names_command() {
+        let (result, _) = faked(vec![ok("Enabled: Yes\nServer: a\nPort: http\n")], || {
+            get_web_proxy(Network::WiFi)
+        });
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err
+            .to_string()
+            .starts_with("networksetup -getwebproxy \"Wi-Fi\" printed unexpected output"));
+    }
+
+    #[test]
+    fn duplicate_network_service_argv() {
+        let (result, ran) = faked(vec![ok(SERVICES), ok("")], || {
+            duplicate_network_service(Network::WiFi, "Wi-Fi Clean")
+        });
+        result.unwrap();
+        assert_eq!(ran[0], ["-listallnetworkservices"]);
+        assert_eq!(ran[1], ["-duplicatenetworkservice", "Wi-Fi", "Wi-Fi Clean"]);
+    }
+
+    #[test]
+    fn duplicate_network_service_rejects_taken_name() {
+        let (result, ran) = faked(vec![ok(SERVICES)], || {
+            duplicate_network_service(Network::WiFi, "Thunderbolt Bridge")
+        });
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::AlreadyExists);
+        assert_eq!(ran.len(), 1);
+    }
+
+    #[test]
+    fn duplicate_network_service_rejects_unknown_source() {
+        let (result, _) = faked(vec![ok(SERVICES)], || {
+            duplicate_network_service(Network::Name("AirPort"), "Copy")
+        });
+        match result.unwrap_err() {
+            Error::UnknownService {
+                command,
+                requested,
+                available,
+            } => {
+                assert_eq!(command, None);
+                assert_eq!(requested, "AirPort");
+                assert_eq!(available, ["Ethernet", "Wi-Fi", "Thunderbolt Bridge"]);
+            }
+            err => panic!("unexpected error {err}"),
+        }
+    }
+
+    #[test]
+    fn set_bootp_argv() {
+        let (result, ran) = faked(vec![ok("")], || set_bootp(Network::Ethernet));
+        result.unwrap();
+        assert_eq!(ran, [["-setbootp", "Ethernet"]]);
+    }
+
+    #[test]
+    fn set_additional_routes_flattens_triplets() {
+        let routes = [
+            Route {
+                dest: Ipv4Addr::new(10, 0, 0, 0),
+                mask: Ipv4Addr::new(255, 0, 0, 0),
+                gateway: Some(Ipv4Addr::new(192, 168, 1, 1)),
+            },
+            Route {
+                dest: Ipv4Addr::new(172, 16, 0, 0),
+                mask: Ipv4Addr::new(255, 240, 0, 0),
+                gateway: None,
+            },
+            Route {
+                dest: Ipv4Addr::new(192, 168, 8, 0),
+                mask: Ipv4Addr::new(255, 255, 255, 0),
+                gateway: Some(Ipv4Addr::new(192, 168, 1, 254)),
+            },
+        ];
+        let (result, ran) = faked(vec![ok("")], || {
+            set_additional_routes(Network::WiFi, &routes)
+        });
+        result.unwrap();
+        assert_eq!(
+            ran,
+            [[
+                "-setadditionalroutes",
+                "Wi-Fi",
+                "10.0.0.0",
+                "255.0.0.0",
+                "192.168.1.1",
+                "172.16.0.0",
+                "255.240.0.0",
+                "",
+                "192.168.8.0",
+                "255.255.255.0",
+                "192.168.1.254",
+            ]]
+        );
+    }
+
+    #[test]
+    fn set_additional_routes_empty_clears() {
+        let (result, ran) = faked(vec![ok("")], || set_additional_routes(Network::WiFi, &[]));
+        result.unwrap();
+        assert_eq!(ran, [["-setadditionalroutes", "Wi-Fi"]]);
+    }
+
+    #[test]
+    fn set_v6_manual_argv_order() {
+        let addr = "2001:db8::10".parse().unwrap();
+        let router = "2001:db8::1".parse().unwrap();
+        let (result, ran) = faked(vec![ok("")], || {
+            set_v6_manual(Network::Ethernet, addr, 64, router)
+        });
+        result.unwrap();
+        assert_eq!(
+            ran,
+            [[
+                "-setv6manual",
+                "Ethernet",
+                "2001:db8::10",
+                "64",
+                "2001:db8::1"
+            ]]
+        );
+    }
+
+    #[test]
+    fn set_v6_manual_rejects_long_prefix() {
+        let (result, ran) = faked(vec![], || {
+            set_v6_manual(
+                Network::Ethernet,
+                Ipv6Addr::LOCALHOST,
+                129,
+                Ipv6Addr::LOCALHOST,
+            )
+        });
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert!(ran.is_empty());
+    }
+
+    #[test]
+    fn vlan_devices_listed() {
+        let text = "Ethernet (en0)\nThunderbolt Ethernet Slot 1 (en7)\nen8\n";
+        assert_eq!(vlan_devices(text), ["en0", "en7", "en8"]);
+    }
+
+    #[test]
+    fn vlan_devices_none() {
+        assert!(vlan_devices("").is_empty());
+        assert!(vlan_devices("There are no devices that support VLANs.\n").is_empty());
+    }
+
+    #[test]
+    fn bonds_one_member() {
+        let text = "interface: bond0\nuser-defined-name: Uplink\ndevices: en1\n";
+        assert_eq!(
+            Bond::parse(text),
+            [Bond {
+                device: "bond0".to_string(),
+                user_defined_name: Some("Uplink".to_string()),
+                members: vec!["en1".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn bonds_multiple_members() {
+        let text = "interface: bond0\n\
+            user-defined-name: Uplink\n\
+            devices: en1, en2\n\
+            \ten3\n\
+            interface: bond1\n\
+            user-defined-name: \n\
+            devices: en4, en5\n";
+        let bonds = Bond::parse(text);
+        assert_eq!(bonds.len(), 2);
+        assert_eq!(bonds[0].members, ["en1", "en2", "en3"]);
+        assert_eq!(bonds[1].device, "bond1");
+        assert_eq!(bonds[1].user_defined_name, None);
+        assert_eq!(bonds[1].members, ["en4", "en5"]);
+    }
+
+    #[test]
+    fn bonds_none() {
+        assert!(Bond::parse("There are no bonds.\n").is_empty());
+        assert!(Bond::parse("").is_empty());
+    }
+
+    #[test]
+    fn bond_status_members() {
+        let text = "Interface: bond0\n\
+            Status: Active\n\
+            Device\tLACP State\tLink\n\
+            \ten1\tActive\t1000baseT full-duplex\n\
+            \ten2\tStandby\t1000baseT full-duplex\n";
+        let status = BondStatus::parse(text).unwrap();
+        assert_eq!(status.status, "Active");
+        assert_eq!(
+            status.members,
+            [
+                BondMemberStatus {
+                    interface: "en1".to_string(),
+                    lacp_state: "Active".to_string(),
+                    link: "1000baseT full-duplex".to_string(),
+                },
+                BondMemberStatus {
+                    interface: "en2".to_string(),
+                    lacp_state: "Standby".to_string(),
+                    link: "1000baseT full-duplex".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn bond_status_without_status() {
+        assert!(BondStatus::parse("\ten1\tActive\t1000baseT\n").is_err());
+    }
+
+    #[test]
+    fn create_pppoe_service_argv() {
+        let (result, ran) = faked(vec![ok("")], || {
+            create_pppoe_service("en0", "DSL", "store42", "hunter2", Some("ISP"))
+        });
+        result.unwrap();
+        assert_eq!(
+            ran,
+            [[
+                "-createpppoeservice",
+                "en0",
+                "DSL",
+                "store42",
+                "<redacted>",
+                "ISP"
+            ]]
+        );
+        let (_, ran) = faked(vec![ok("")], || {
+            create_pppoe_service("en0", "DSL", "store42", "hunter2", None)
+        });
+        assert_eq!(
+            ran,
+            [["-createpppoeservice", "en0", "DSL", "store42", "<redacted>"]]
+        );
+    }
+
+    #[test]
+    fn delete_missing_pppoe_service() {
+        let (result, ran) = faked(vec![ok("DSL\n")], || delete_pppoe_service("Fiber"));
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(ran, [["-listpppoeservices"]]);
+    }
+
+    #[test]
+    fn connect_pppoe_argv() {
+        let services = "DSL\nDSL Uplink\n";
+        let (result, ran) = faked(vec![ok(services), ok("")], || connect_pppoe("DSL Uplink"));
+        result.unwrap();
+        assert_eq!(
+            ran,
+            [
+                vec!["-listpppoeservices"],
+                vec!["-connectpppoeservice", "DSL Uplink"]
+            ]
+        );
+        let (result, ran) = faked(vec![ok(services), ok("")], || {
+            disconnect_pppoe("DSL Uplink")
+        });
+        result.unwrap();
+        assert_eq!(ran[1], ["-disconnectpppoeservice", "DSL Uplink"]);
+    }
+
+    #[test]
+    fn connect_missing_pppoe_service() {
+        for services in ["DSL\n", "There aren't any PPPoE services.\n"] {
+            let (result, ran) = faked(vec![ok(services)], || connect_pppoe("DSL Uplink"));
+            assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+            assert_eq!(ran, [["-listpppoeservices"]]);
+        }
+        let (result, ran) = faked(vec![ok("DSL\n")], || disconnect_pppoe("DSL Uplink"));
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(ran, [["-listpppoeservices"]]);
+    }
+
+    #[test]
+    fn set_6to4_manual_argv() {
+        let relay = "2002:c058:6301::".parse().unwrap();
+        let (result, ran) = faked(vec![ok("")], || set_6to4_manual("6to4", relay));
+        result.unwrap();
+        assert_eq!(ran, [["-set6to4manual", "6to4", "2002:c058:6301::"]]);
+    }
+
+    fn line() -> CommandLine {
+        CommandLine {
+            program: "networksetup".to_string(),
+            args: vec!["-setwebproxy".to_string(), "Wi-Fi".to_string()],
+        }
+    }
+
+    #[test]
+    fn check_success() {
+        let output = check(&line(), ok("")).unwrap();
+        assert!(output.status.success());
+    }
+
+    #[test]
+    fn check_non_zero_exit_fails() {
+        match check(&line(), reply(4, "", "something went wrong\n")).unwrap_err() {
+            Error::Failed {
+                command,
+                status,
+                stderr,
+                ..
+            } => {
+                assert_eq!(command, line());
+                assert_eq!(status.code(), Some(4));
+                assert_eq!(stderr, "something went wrong\n");
+            }
+            err => panic!("unexpected error {err}"),
+        }
+    }
+
+    #[test]
+    fn check_error_on_stdout_fails() {
+        let err = check(&line(), ok("** Error: The parameters were not valid.\n")).unwrap_err();
+        assert!(matches!(err, Error::Failed { .. }));
+        assert_eq!(
+            err.to_string(),
+            "networksetup -setwebproxy \"Wi-Fi\" failed: ** Error: The parameters were not valid."
+        );
+    }
+
+    #[test]
+    fn setters_report_non_zero_exit() {
+        let addr = Address::new("127.0.0.1", "8080");
+        let setters: [&dyn Fn() -> Result<()>; 10] = [
+            &|| web_proxy(Network::WiFi, Config::Value(&addr)),
+            &|| secure_web_proxy(Network::WiFi, Config::On),
+            &|| socks_proxy(Network::WiFi, Config::Value(&addr)),
+            &|| streaming_proxy(Network::WiFi, Config::Off),
+            &|| auto_proxy(Network::WiFi, Config::Value("http://example.com/proxy.pac")),
+            &|| auto_proxy_discovery(Network::WiFi, true),
+            &|| passive_ftp(Network::WiFi, false),
+            &|| proxy_by_pass_domain(Network::WiFi, &["*.local"]),
+            &|| dns_server(Network::WiFi, &["1.1.1.1"]),
+            &|| search_domains(Network::WiFi, &["example.com"]),
+        ];
+        for setter in setters {
+            let (result, _) = faked(vec![reply(1, "", "failed\n")], setter);
+            assert!(matches!(result, Err(Error::Failed { .. })));
+            let (result, _) = faked(vec![ok("")], setter);
+            result.unwrap();
+        }
+    }
+
+    #[test]
+    fn denied_fixtures() {
+        for text in [
+            // networksetup as a standard user
+            "** Error: Command requires admin privileges.",
+            "Command requires admin privileges.",
+            // airport and ifconfig when not run as root
+            "en0: Operation not permitted",
+            "ifconfig: ioctl (SIOCSIFMTU): Operation not permitted",
+            "This tool must be run as root.",
+            // killall -HUP mDNSResponder as a standard user
+            "No matching processes belonging to you were found",
+        ] {
+            assert!(denied(text), "{text}");
+        }
+    }
+
+    #[test]
+    fn ordinary_failures_are_not_denied() {
+        for text in [
+            "** Error: The parameters were not valid.",
+            "Wi-Fi is not a recognized network service.",
+            "Could not find network Office.",
+            "Failed to join network Office.\nError: -3905  The operation couldn't be completed.",
+            "802.1X authorization failed for the login profile",
+            "The network administrator has not allowed this profile",
+            "No matching processes were found",
+        ] {
+            assert!(!denied(text), "{text}");
+        }
+    }
+
+    #[test]
+    fn check_permission_denied() {
+        let output = reply(14, "** Error: Command requires admin privileges.\n", "");
+        match check(&line(), output).unwrap_err() {
+            Error::PermissionDenied { command, message } => {
+                assert_eq!(command, line());
+                assert_eq!(message, "** Error: Command requires admin privileges.");
+            }
+            err => panic!("unexpected error {err}"),
+        }
+    }
+
+    #[test]
+    fn unknown_service_looks_up_available() {
+        let (result, ran) = faked(
+            vec![
+                ok("Foo is not a recognized network service.\n** Error: The parameters were not valid.\n"),
+                ok(SERVICES),
+            ],
+            || get_web_proxy(Network::Name("Foo")),
+        );
+        match result.unwrap_err() {
+            Error::UnknownService {
+                command,
+                requested,
+                available,
+            } => {
+                assert_eq!(command.unwrap().args, ["-getwebproxy", "Foo"]);
+                assert_eq!(requested, "Foo");
+                assert_eq!(available, ["Ethernet", "Wi-Fi", "Thunderbolt Bridge"]);
+            }
+            err => panic!("unexpected error {err}"),
+        }
+        assert_eq!(ran[1], ["-listallnetworkservices"]);
+    }
+
+    #[test]
+    fn unknown_service_lists_from_message() {
+        let text = "AirPort is not a recognized network service.\nWi-Fi\n*Ethernet\n";
+        match unknown_service(&line(), text) {
+            Some(Error::UnknownService {
+                command,
+                requested,
+                available,
+            }) => {
+                assert_eq!(command, Some(line()));
+                assert_eq!(requested, "AirPort");
+                assert_eq!(available, ["Wi-Fi", "Ethernet"]);
+            }
+            _ => panic!("not recognized"),
+        }
+        assert!(unknown_service(&line(), "** Error: The parameters were not valid.\n").is_none());
+    }
+
+    #[test]
+    fn replay_keeps_disabled_socks_server() {
+        let snapshot = ServiceSnapshot {
+            name: "Wi-Fi".to_string(),
+            proxies: vec![
+                (&PROXIES[0], Err(Error::FtpProxyUnsupported)),
+                (
+                    &PROXIES[3],
+                    Ok(ProxySettings {
+                        enabled: false,
+                        host: "127.0.0.1".to_string(),
+                        port: 1080,
+                        authenticated: false,
+                    }),
+                ),
+            ],
+            auto_proxy: Err(invalid_data("")),
+            bypass_domains: Err(invalid_data("")),
+            dns_servers: Err(invalid_data("")),
+            search_domains: Err(invalid_data("")),
+            info: Err(invalid_data("")),
+        };
+        let mut report = LocationReport::default();
+        let (_, ran) = faked(vec![ok(""), ok("")], || snapshot.replay(&mut report));
+        assert_eq!(
+            ran,
+            [
+                ["-setsocksfirewallproxy", "Wi-Fi", "127.0.0.1", "1080"].as_slice(),
+                ["-setsocksfirewallproxystate", "Wi-Fi", "off"].as_slice(),
+            ]
+        );
+        assert_eq!(report.applied, [("Wi-Fi".to_string(), "socks proxy")]);
+        assert!(!report
+            .failed
+            .iter()
+            .any(|(_, setting, _)| *setting == "ftp proxy"));
+    }
+
+    #[test]
+    fn ftp_proxy_unsupported_variant() {
+        // PRINTCOMMANDS lists no FTP subcommands
+        let (result, ran) = faked(vec![], || get_ftp_proxy(Network::WiFi));
+        assert!(matches!(result, Err(Error::FtpProxyUnsupported)));
+        assert!(ran.is_empty());
+        let err = ftp_error(Error::failed(
+            &line(),
+            reply(1, "", "-setftpproxy is no longer supported\n"),
+        ));
+        assert!(matches!(err, Error::FtpProxyUnsupported));
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn require_names_unsupported_command() {
+        let (result, _) = faked(vec![], || require(true, "-setgopherproxy"));
+        match result.unwrap_err() {
+            Error::Unsupported { command } => assert_eq!(command, "-setgopherproxy"),
+            err => panic!("unexpected error {err}"),
+        }
+    }
+
+    #[test]
+    fn delete_current_location_switches_to_another() {
+        let (result, ran) = faked(
+            vec![
+                ok("Home\n"),
+                ok("Home\nOffice\n"),
+                ok("found it!\n"),
+                ok("Office\n"),
+                ok(""),
+            ],
+            || delete_location("Home", true),
+        );
+        result.unwrap();
+        assert_eq!(ran[2], ["-switchtolocation", "Office"]);
+        assert_eq!(ran[4], ["-deletelocation", "Home"]);
+    }
+
+    #[test]
+    fn delete_only_location_is_refused() {
+        let (result, ran) = faked(vec![ok("Home\n"), ok("Home\n")], || {
+            delete_location("Home", true)
+        });
+        let err = result.unwrap_err();
+        assert_eq!(err.downcast_ref(), Some(&OnlyLocation("Home".to_string())));
+        assert_eq!(ran.len(), 2);
+    }
+
+    #[test]
+    #[cfg(not(feature = "keychain"))]
+    fn move_secured_network_without_password_is_refused() {
+        let (result, ran) = faked(
+            vec![ok("Preferred networks on en0:\n\tHome\n\tOffice\n")],
+            || move_preferred_wireless_network("en0", "Office", 0, WifiSecurity::Wpa2, None),
+        );
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(ran, [["-listpreferredwirelessnetworks", "en0"]]);
+    }
+
+    #[test]
+    fn move_preferred_wireless_network_argv() {
+        let (result, ran) = faked(
+            vec![
+                ok("Preferred networks on en0:\n\tHome\n\tOffice\n"),
+                ok(""),
+                ok(""),
+            ],
+            || move_preferred_wireless_network("en0", "Office", 0, WifiSecurity::Wpa2, Some("pw")),
+        );
+        result.unwrap();
+        assert_eq!(ran[1], ["-removepreferredwirelessnetwork", "en0", "Office"]);
+        assert_eq!(
+            ran[2],
+            [
+                "-addpreferredwirelessnetworkatindex",
+                "en0",
+                "Office",
+                "0",
+                "WPA2",
+                "<redacted>"
+            ]
+        );
+    }
+
+    fn export_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("networksetup-{}-{name}.xml", std::process::id()))
+    }
+
+    #[test]
+    fn export_8021x_profiles_keeps_backup_when_nothing_is_written() {
+        let path = export_path("empty");
+        fs::write(&path, "<plist/>").unwrap();
+        let (result, ran) = faked(vec![ok("")], || {
+            export_8021x_profiles(Network::Ethernet, &path, false)
+        });
+        let err = result.unwrap_err();
+        assert!(err.downcast_ref::<NoProfilesToExport>().is_some());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "<plist/>");
+        assert_eq!(ran[0][0], "-export8021xProfiles");
+        assert!(!Path::new(&ran[0][2]).exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn export_8021x_profiles_replaces_backup() {
+        let path = export_path("written");
+        fs::write(&path, "<plist/>").unwrap();
+        let result = command::fake(
+            |line| {
+                fs::write(&line.args[2], "<plist>new</plist>").unwrap();
+                ok("")
+            },
+            || export_8021x_profiles(Network::Ethernet, &path, true),
+        );
+        result.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "<plist>new</plist>");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn flush_dns_cache_without_root_is_denied() {
+        let (result, ran) = faked(
+            vec![
+                ok(""),
+                reply(1, "", "No matching processes belonging to you were found\n"),
+            ],
+            flush_dns_cache,
+        );
+        match result.unwrap_err() {
+            Error::PermissionDenied { command, .. } => {
+                assert_eq!(command.to_string(), "killall -HUP \"mDNSResponder\"");
+            }
+            err => panic!("unexpected error {err}"),
+        }
+        assert_eq!(ran, [vec!["-flushcache"], vec!["-HUP", "mDNSResponder"]]);
+    }
+
+    #[test]
+    fn failed_probe_is_cached() {
+        let cell = OnceLock::new();
+        let err = cached(&cell, || Err(invalid_data("no subcommands listed"))).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = cached(&cell, || panic!("probed again")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Other);
+    }
+
+    #[test]
+    fn wifi_ssid_containing_error_is_not_a_failure() {
+        let (result, _) = faked(
+            vec![ok("Removed Error Prone from the preferred networks list\n")],
+            || remove_preferred_wireless_network("en0", "Error Prone"),
+        );
+        result.unwrap();
+        let (result, _) = faked(vec![ok("")], || {
+            add_preferred_wireless_network("en0", 0, "not found", WifiSecurity::Open, None)
+        });
+        result.unwrap();
+    }
+
+    #[test]
+    fn wifi_error_lines_fail() {
+        let (result, _) = faked(
+            vec![ok(
+                "** Error: Network Office is not in the preferred networks list\n",
+            )],
+            || remove_preferred_wireless_network("en0", "Office"),
+        );
+        let err = result.unwrap_err();
+        assert_eq!(err.downcast_ref(), Some(&NotInList("Office".to_string())));
+        let (result, _) = faked(vec![ok("Error: -3900  Could not add Office\n")], || {
+            add_preferred_wireless_network("en0", 0, "Office", WifiSecurity::Open, None)
+        });
+        assert!(matches!(result, Err(Error::Failed { .. })));
+    }
+
+    #[test]
+    fn auth_debug_redacts_password() {
+        let mut address = Address::new("proxy.local", "8080");
+        address.auth("user", "hunter2");
+        let debug = format!("{address:?}");
+        assert!(
+            debug.contains(r#"Credentials("user", <redacted>)"#),
+            "{debug}"
+        );
+        assert!(!debug.contains("hunter2"));
+    }
+
+    const SHORT: Duration = Duration::from_millis(50);
+
+    #[test]
+    fn timeout_kills_and_reaps_command() {
+        let pid_file =
+            std::env::temp_dir().join(format!("networksetup-{}.pid", std::process::id()));
+        let mut cmd = Cmd::new("sh");
+        cmd.arg("-c")
+            .arg(format!("echo $$ > {}; exec sleep 5", pid_file.display()));
+        // Long enough for the shell to write its pid before it is killed
+        let start = Instant::now();
+        let result = with_timeout(Some(SHORT * 10), || cmd.output());
+        assert!(matches!(result, Err(Error::TimedOut { .. })));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        let pid = fs::read_to_string(&pid_file).unwrap();
+        fs::remove_file(&pid_file).unwrap();
+        // A zombie still answers kill -0, only a reaped child is gone
+        let alive = std::process::Command::new("kill")
+            .args(["-0", pid.trim()])
+            .stderr(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(!alive.success());
+    }
+
+    #[test]
+    fn timeout_covers_pipes_held_by_background_process() {
+        let mut cmd = Cmd::new("sh");
+        cmd.args(["-c", "sleep 3 &"]);
+        let start = Instant::now();
+        let result = with_timeout(Some(SHORT), || cmd.output());
+        assert!(matches!(result, Err(Error::TimedOut { .. })));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn timeout_allows_quick_command() {
+        let mut cmd = Cmd::new("echo");
+        cmd.arg("done");
+        let output = with_timeout(Some(Duration::from_secs(5)), || cmd.output()).unwrap();
+        assert_eq!(output.stdout, b"done\n");
+    }
+
+    #[test]
+    fn replay_keeps_dhcp_client_id() {
+        let snapshot = ServiceSnapshot {
+            name: "Ethernet".to_string(),
+            proxies: Vec::new(),
+            auto_proxy: Err(invalid_data("")),
+            bypass_domains: Err(invalid_data("")),
+            dns_servers: Err(invalid_data("")),
+            search_domains: Err(invalid_data("")),
+            info: Ok(ServiceInfo {
+                method: ConfigMethod::Dhcp,
+                ip_address: None,
+                subnet_mask: None,
+                router: None,
+                client_id: Some("office-desk".to_string()),
+                ipv6: Ipv6Method::Automatic,
+                ipv6_address: None,
+                ipv6_prefix_length: None,
+                ipv6_router: None,
+                hardware_address: None,
+            }),
+        };
+        let mut report = LocationReport::default();
+        let (_, ran) = faked(
+            vec![ok(""), ok(""), ok("DHCP Configuration\nIPv6: Automatic\n")],
+            || snapshot.replay(&mut report),
+        );
+        assert_eq!(ran[0], ["-setdhcp", "Ethernet", "office-desk"]);
+        assert_eq!(ran[1], ["-setv6automatic", "Ethernet"]);
+        assert_eq!(
+            report.applied,
+            [
+                ("Ethernet".to_string(), "ipv4"),
+                ("Ethernet".to_string(), "ipv6")
+            ]
+        );
+    }
+
+    #[test]
+    fn service_info_dhcp_on_wifi() {
+        let text = "DHCP Configuration\n\
+            IP address: 192.168.1.23\n\
+            Subnet mask: 255.255.255.0\n\
+            Router: 192.168.1.1\n\
+            Client ID: \n\
+            IPv6: Automatic\n\
+            IPv6 IP address: none\n\
+            IPv6 Router: none\n\
+            Wi-Fi ID: a4:83:e7:12:34:56\n";
+        assert_eq!(
+            ServiceInfo::parse(text).unwrap(),
+            ServiceInfo {
+                method: ConfigMethod::Dhcp,
+                ip_address: Some(Ipv4Addr::new(192, 168, 1, 23)),
+                subnet_mask: Some(Ipv4Addr::new(255, 255, 255, 0)),
+                router: Some(Ipv4Addr::new(192, 168, 1, 1)),
+                client_id: None,
+                ipv6: Ipv6Method::Automatic,
+                ipv6_address: None,
+                ipv6_prefix_length: None,
+                ipv6_router: None,
+                hardware_address: Some(MacAddress::new([0xa4, 0x83, 0xe7, 0x12, 0x34, 0x56])),
+            }
+        );
+    }
+
+    #[test]
+    fn service_info_manual_on_ethernet() {
+        let text = "Manual Configuration\n\
+            IP address: 10.0.0.5\n\
+            Subnet mask: 255.255.255.0\n\
+            Router: 10.0.0.1\n\
+            IPv6: Manual\n\
+            IPv6 IP address: 2001:db8::5\n\
+            IPv6 Prefix Length: 64\n\
+            IPv6 Router: 2001:db8::1\n\
+            Ethernet Address: 00:1c:42:aa:bb:cc\n";
+        let info = ServiceInfo::parse(text).unwrap();
+        assert_eq!(info.method, ConfigMethod::Manual);
+        assert_eq!(info.ip_address, Some(Ipv4Addr::new(10, 0, 0, 5)));
+        assert_eq!(info.router, Some(Ipv4Addr::new(10, 0, 0, 1)));
+        assert_eq!(info.ipv6, Ipv6Method::Manual);
+        assert_eq!(info.ipv6_address, Some("2001:db8::5".parse().unwrap()));
+        assert_eq!(info.ipv6_prefix_length, Some(64));
+        assert_eq!(info.ipv6_router, Some("2001:db8::1".parse().unwrap()));
+        assert_eq!(
+            info.hardware_address,
+            Some(MacAddress::new([0x00, 0x1c, 0x42, 0xaa, 0xbb, 0xcc]))
+        );
+    }
+
+    #[test]
+    fn service_info_ipv4_off() {
+        let text = "IPv6: Off\nIPv6 IP address: none\nIPv6 Router: none\n\
+            Ethernet Address: (null)\n";
+        let info = ServiceInfo::parse(text).unwrap();
+        assert_eq!(info.method, ConfigMethod::Off);
+        assert_eq!(info.ip_address, None);
+        assert_eq!(info.ipv6, Ipv6Method::Off);
+        assert_eq!(info.hardware_address, None);
+    }
+
+    #[test]
+    fn service_info_dhcp_router_and_client_id() {
+        let text = "Manual with DHCP Router Configuration\n\
+            IP address: 192.168.1.50\n\
+            Subnet mask: none\n\
+            Router: none\n\
+            Client ID: office-desk\n\
+            IPv6: Automatic\n";
+        let info = ServiceInfo::parse(text).unwrap();
+        assert_eq!(info.method, ConfigMethod::ManualWithDhcpRouter);
+        assert_eq!(info.subnet_mask, None);
+        assert_eq!(info.router, None);
+        assert_eq!(info.client_id.as_deref(), Some("office-desk"));
+        assert!(ServiceInfo::parse("** Error: The parameters were not valid.\n").is_err());
     }
-    cmd.status()
 }
@@ -0,0 +1,63 @@
+//! Effective resolver configuration reported by `scutil --dns`
+//!
+//! Unlike `get_dns_servers`, this includes resolvers supplied by DHCP or a VPN.
+
+use crate::{fields, output, Cmd, Result};
+
+/// A `resolver #n` block
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolverConfig {
+    /// Domain this resolver is used for, `None` for the default resolver
+    pub domain: Option<String>,
+    /// Name servers, IPv6 link-local addresses keep their zone (`fe80::1%en0`)
+    pub nameservers: Vec<String>,
+    pub search_domains: Vec<String>,
+    /// Interface name from `if_index`, e.g. `en0`
+    pub interface: Option<String>,
+    pub order: Option<u32>,
+    /// Listed under "DNS configuration (for scoped queries)"
+    pub scoped: bool,
+}
+
+/// The resolvers the system is actually using
+pub fn effective_dns() -> Result<Vec<ResolverConfig>> {
+    let mut cmd = Cmd::new("scutil");
+    cmd.arg("--dns");
+    Ok(parse(&output(&mut cmd)?))
+}
+
+fn parse(text: &str) -> Vec<ResolverConfig> {
+    let mut resolvers = Vec::new();
+    let mut scoped = false;
+    let mut current: Option<ResolverConfig> = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with("DNS configuration") {
+            resolvers.extend(current.take());
+            scoped = line.contains("scoped");
+        } else if line.starts_with("resolver #") {
+            resolvers.extend(current.take());
+            current = Some(ResolverConfig {
+                scoped,
+                ..Default::default()
+            });
+        } else if let (Some(resolver), Some((key, value))) = (&mut current, fields(line).next()) {
+            match key {
+                "domain" => resolver.domain = Some(value.to_string()),
+                "order" => resolver.order = value.parse().ok(),
+                "if_index" => {
+                    resolver.interface = value
+                        .split_once('(')
+                        .map(|(_, name)| name.trim_end_matches(')').to_string());
+                }
+                _ if key.starts_with("nameserver[") => resolver.nameservers.push(value.to_string()),
+                _ if key.starts_with("search domain[") => {
+                    resolver.search_domains.push(value.to_string())
+                }
+                _ => {}
+            }
+        }
+    }
+    resolvers.extend(current);
+    resolvers
+}